@@ -7,19 +7,22 @@ use std::time::Instant;
 use anyhow::{Context, Result};
 use egui::ahash::HashMap;
 use egui::{Align, Grid, Layout, ScrollArea, TextEdit, UiBuilder};
-use hades2::saves::{LuaValue, Savefile};
+use hades2::game::{self, RESOURCES, resource_info};
+use hades2::saves::{LuaPath, LuaValue, Savefile, Walk};
 use hades2::{Hades2Installation, SaveHandle};
 
-use self::luavalue::Pos;
-
 mod luavalue;
 
+use luavalue::Bookmarks;
+
 const AUTOLOAD: bool = cfg!(debug_assertions) && true;
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct App {
     advanced_mode: bool,
+    /// Shown above the tree in advanced mode
+    bookmarks: Bookmarks,
 
     #[serde(skip)]
     save_dialog: Option<SaveDialog>,
@@ -34,7 +37,7 @@ struct State {
     hades: Option<Hades2Installation>,
     error: Option<String>,
 
-    saves: Vec<(SaveHandle, Result<Savefile>, bool)>,
+    saves: Vec<(SaveHandle, hades2::Result<Savefile>, bool)>,
 }
 
 struct FilterState {
@@ -42,7 +45,7 @@ struct FilterState {
     filter_changed: bool,
     search_values: bool,
 
-    cached_visible: HashMap<Pos, bool>,
+    cached_visible: HashMap<LuaPath<'static>, bool>,
 }
 
 struct CurrentSavefile {
@@ -56,7 +59,7 @@ struct CurrentSavefile {
 }
 
 struct SaveDialog {
-    backups: Option<Vec<(SaveHandle, Result<u64>)>>,
+    backups: Option<Vec<(SaveHandle, hades2::Result<u64>)>>,
 }
 
 impl App {
@@ -64,11 +67,14 @@ impl App {
         self.state.hades.as_ref()
     }
 
-    fn handle_error<T>(&mut self, result: Result<T>) -> Option<T> {
+    fn handle_error<T, E: Into<anyhow::Error>>(
+        &mut self,
+        result: std::result::Result<T, E>,
+    ) -> Option<T> {
         match result {
             Ok(val) => Some(val),
             Err(e) => {
-                self.state.error = Some(e.to_string());
+                self.state.error = Some(format!("{:#}", e.into()));
                 None
             }
         }
@@ -96,27 +102,25 @@ impl App {
                     .path()
                     .file_stem()
                     .and_then(OsStr::to_str)
-                    .map_or(false, |stem| stem == active_profile);
+                    .is_some_and(|stem| stem == active_profile);
                 (handle, save, is_active)
             })
             .collect::<Vec<_>>();
 
-        if AUTOLOAD {
-            if let [.., (handle, _, _)] = self.state.saves.as_slice() {
-                let (save, lua_state) = handle.read().unwrap();
-                self.current_savefile = Some(Box::new(CurrentSavefile {
-                    filter: FilterState {
-                        filter_changed: true,
-                        filter: String::new(),
-                        search_values: false,
-                        cached_visible: HashMap::default(),
-                    },
-                    handle: handle.clone(),
-                    save,
-                    lua_state,
-                    dirty: false,
-                }));
-            }
+        if AUTOLOAD && let [.., (handle, _, _)] = self.state.saves.as_slice() {
+            let (save, lua_state) = handle.read().unwrap();
+            self.current_savefile = Some(Box::new(CurrentSavefile {
+                filter: FilterState {
+                    filter_changed: true,
+                    filter: String::new(),
+                    search_values: false,
+                    cached_visible: HashMap::default(),
+                },
+                handle: handle.clone(),
+                save,
+                lua_state,
+                dirty: false,
+            }));
         }
 
         Ok(())
@@ -127,7 +131,6 @@ impl App {
         let mut save_result = None;
         if let Some(save_dialog) = &self.save_dialog {
             let current = self.current_savefile.as_deref().unwrap();
-            current.save.timestamp;
 
             egui::Window::new("Save")
                 .title_bar(false)
@@ -161,7 +164,7 @@ impl App {
                                         Err(e) => {
                                             ui.horizontal(|ui| {
                                                 ui.label(bak.to_string());
-                                                show_error(ui, e.to_string())
+                                                show_error(ui, error_chain(e))
                                             });
                                         }
                                     }
@@ -229,6 +232,7 @@ impl Default for App {
         };
         let mut app = Self {
             advanced_mode: false,
+            bookmarks: Bookmarks::default(),
             save_dialog: None,
             state: State {
                 hades,
@@ -278,11 +282,9 @@ impl eframe::App for App {
                 None => "Savefile Editor".to_string(),
             };
             ui.horizontal(|ui| {
-                if self.current_savefile.is_some() {
-                    if ui.button("⏴").clicked() {
-                        self.current_savefile = None;
-                        self.reset_error();
-                    }
+                if self.current_savefile.is_some() && ui.button("⏴").clicked() {
+                    self.current_savefile = None;
+                    self.reset_error();
                 }
                 ui.heading(heading);
             });
@@ -421,47 +423,83 @@ impl App {
             }
 
             ScrollArea::vertical().show(ui, |ui| {
+                if !self.bookmarks.is_empty() {
+                    *dirty |= show_bookmarks(ui, lua_state, &mut self.bookmarks);
+                    ui.separator();
+                }
+
                 *dirty |= luavalue::show_value(
                     ui,
                     lua_state,
-                    Pos::default(),
+                    &mut LuaPath::new(),
                     Some(&filter.cached_visible),
+                    &mut self.bookmarks,
                 );
+                self.bookmarks.apply();
                 ui.allocate_space(ui.available_size());
             });
         } else {
             Grid::new("easy mode").show(ui, |ui| {
                 let mut runs_human = save.runs + 1;
-                changed |= numeric(ui, "Runs", &mut runs_human);
-                save.runs = runs_human.saturating_sub(1);
-                changed |= numeric(ui, "Grasp", &mut save.grasp);
+                if numeric(ui, "Runs", &mut runs_human) {
+                    save.runs = runs_human.saturating_sub(1);
+                    if let Some(completed) = lua_state.get_path_mut("GameState.CompletedRunsCache")
+                    {
+                        let result = completed.set_number(save.runs.into());
+                        self.handle_error(result);
+                    }
+                    changed = true;
+                }
+                let mut grasp = save.grasp;
+                if numeric(ui, "Grasp", &mut grasp) {
+                    let result = game::State::new(&mut *lua_state)
+                        .cards_mut()
+                        .and_then(|mut cards| cards.set_grasp(save, grasp));
+                    changed |= self.handle_error(result).is_some();
+                }
 
-                changed |= valpath::<u32>(
-                    ui,
-                    "Ash",
-                    "GameState.Resources.CardUpgradePoints",
-                    lua_state,
-                );
-                changed |= valpath::<u32>(
-                    ui,
-                    "Charon Cards",
-                    "GameState.Resources.CharonPoints",
-                    lua_state,
-                );
+                let mut god_mode = save.easy_mode;
+                if checkbox(ui, "God Mode", &mut god_mode) {
+                    let result = game::State::new(&mut *lua_state)
+                        .settings_mut()
+                        .and_then(|mut settings| settings.set_god_mode(save, god_mode));
+                    changed |= self.handle_error(result).is_some();
+                }
+
+                changed |= resources(ui, lua_state);
 
                 /*changed |= numeric(ui, "Meta Points", &mut save.accumulated_meta_points);
                 changed |= numeric(ui, "Active Shrine Points", &mut save.active_shrine_points);
-                changed |= checkbox(ui, "Easy Mode", &mut save.easy_mode);
                 changed |= checkbox(ui, "Hard Mode", &mut save.hard_mode);*/
 
                 *dirty |= changed;
             });
 
             ui.add_space(8.0);
-            ui.add_enabled_ui(false, |ui| {
-                let _ = ui.button("Unlock all cards");
-                let _ = ui.button("Max out relationships");
-            });
+            if ui.button("Unlock all cards").clicked() {
+                let result = game::State::new(&mut *lua_state)
+                    .cards_mut()
+                    .and_then(|mut cards| cards.unlock_all());
+                *dirty |= self.handle_error(result).is_some();
+            }
+            if ui.button("Max out relationships").clicked() {
+                let result = game::State::new(&mut *lua_state)
+                    .relationships_mut()
+                    .and_then(|mut relationships| relationships.max_out());
+                *dirty |= self.handle_error(result).is_some();
+            }
+
+            let inconsistencies = game::check_consistency(save, lua_state);
+            if !inconsistencies.is_empty() {
+                ui.add_space(8.0);
+                for inconsistency in &inconsistencies {
+                    show_error(ui, inconsistency.to_string());
+                }
+                if ui.button("Update header from game state").clicked() {
+                    game::sync_header_from_state(save, lua_state);
+                    *dirty = true;
+                }
+            }
         }
 
         ui.allocate_new_ui(
@@ -497,7 +535,7 @@ impl App {
 
         ui.with_layout(Layout::bottom_up(Align::LEFT), |ui| {
             ui.horizontal(|ui| {
-                ui.add_enabled_ui(*dirty || true, |ui| {
+                ui.add_enabled_ui(true, |ui| {
                     if ui.button("Save").clicked() {
                         self.reset_error();
                         let res = self.hades().map(|hades| {
@@ -551,7 +589,7 @@ fn format_ago(seconds: i64) -> String {
     }
 
     let days = hours / 24;
-    return format!("{} days ago", days);
+    format!("{} days ago", days)
 }
 
 fn matches_filter(
@@ -584,96 +622,146 @@ fn matches_filter(
         || (search_values && matches(&val.primitive_to_str().unwrap_or_default().to_lowercase()))
 }
 
-fn record_filter<'l>(
+fn record_filter(
     root: &LuaValue,
     filter_lowercase: &str,
     search_values: bool,
-) -> HashMap<Pos, bool> {
+) -> HashMap<LuaPath<'static>, bool> {
     // INVARIANT: if X in nodes_visible then ancestors(X) in nodes_visible
     let mut nodes_visible = HashMap::default();
 
-    let mut ancestor_scratch = Vec::new();
-    visit_with_ancestors(
-        root,
-        &mut ancestor_scratch,
-        &mut |key, val, ancestors, pos| {
-            if matches_filter(key, val, filter_lowercase, search_values) {
-                nodes_visible.insert(pos, true);
-
-                for ancestor in ancestors.iter().rev() {
-                    let was_occupied = match nodes_visible.entry(ancestor.clone()) {
-                        Entry::Occupied(_) => true,
-                        Entry::Vacant(vacant) => {
-                            vacant.insert(false);
-                            false
-                        }
-                    };
-                    if was_occupied {
-                        // break;
+    root.walk(&mut |path, val| {
+        let Some(key) = path.segments().last() else {
+            return Walk::Continue;
+        };
+        if matches_filter(key, val, filter_lowercase, search_values) {
+            nodes_visible.insert(path.clone().into_owned(), true);
+
+            for ancestor in path.ancestors().rev() {
+                match nodes_visible.entry(ancestor.into_owned()) {
+                    Entry::Occupied(_) => break,
+                    Entry::Vacant(vacant) => {
+                        vacant.insert(false);
                     }
                 }
             }
-        },
-        Pos::default(),
-    );
+        }
+        Walk::Continue
+    });
 
     nodes_visible
 }
 
-pub fn visit_with_ancestors<'l>(
-    val: &'l LuaValue<'l>,
-    ancestors: &mut Vec<Pos>,
-    f_key: &mut impl FnMut(&'l LuaValue<'l>, &'l LuaValue<'l>, &[Pos], Pos),
-    pos: Pos,
-) {
-    match val {
-        LuaValue::Nil => {}
-        LuaValue::Bool(_) => {}
-        LuaValue::Number(_) => {}
-        LuaValue::String(_) => {}
-        LuaValue::Table(table) => {
-            ancestors.push(pos.clone());
-            for (i, (key, val)) in table.iter().enumerate() {
-                let new_pos = pos.push(i.try_into().unwrap());
-                f_key(key, val, ancestors.as_slice(), new_pos.clone());
-                visit_with_ancestors(val, ancestors, f_key, new_pos);
+fn show_bookmarks(ui: &mut egui::Ui, lua_state: &mut LuaValue, bookmarks: &mut Bookmarks) -> bool {
+    let mut changed = false;
+
+    // the bookmarked subtrees are also in the tree below, so their ids need a different salt
+    ui.push_id("bookmarks", |ui| {
+        Grid::new("bookmarks").show(ui, |ui| {
+            for i in 0..bookmarks.items.len() {
+                let text = bookmarks.items[i].0.as_str();
+                let response = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+                response.context_menu(|ui| {
+                    if ui.button("Remove bookmark").clicked() {
+                        bookmarks.toggle(bookmarks.items[i].0.clone());
+                        ui.close_menu();
+                    }
+                });
+
+                let mut path = match &mut bookmarks.items[i].1 {
+                    Ok(path) => std::mem::take(path),
+                    Err(e) => {
+                        show_error(ui, e.as_str());
+                        ui.end_row();
+                        continue;
+                    }
+                };
+                match path.resolve_mut(lua_state) {
+                    Some(value) => {
+                        ui.vertical(|ui| {
+                            changed |= luavalue::show_value(ui, value, &mut path, None, bookmarks);
+                        });
+                    }
+                    None => show_error(ui, "not found"),
+                }
+                // toggles only apply after the frame, so the bookmark is still at `i`
+                bookmarks.items[i].1 = Ok(path);
+                ui.end_row();
             }
-            ancestors.pop();
-        }
+        });
+    });
+
+    changed
+}
+
+/// Formats an error with all its sources, like `{:#}` does for [`anyhow::Error`]
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message = format!("{message}: {error}");
+        source = error.source();
     }
+    message
 }
 
 fn show_error(ui: &mut egui::Ui, error: impl Into<String>) {
     ui.label(egui::RichText::new(error).color(egui::Color32::from_rgb(255, 51, 51)));
 }
 
-fn valpath<T: egui::emath::Numeric>(
-    ui: &mut egui::Ui,
-    label: &str,
-    path: &str,
-    lua_state: &mut LuaValue<'_>,
-) -> bool {
-    ui.label(label);
+/// One row per resource of the catalogue, grouped by category,
+/// followed by the unknown resources in `GameState.Resources`
+fn resources(ui: &mut egui::Ui, lua_state: &mut LuaValue<'_>) -> bool {
+    let mut state = game::State::new(lua_state);
+    let mut resources = match state.resources_mut() {
+        Ok(resources) => resources,
+        Err(e) => {
+            show_error(ui, e.to_string());
+            ui.end_row();
+            return false;
+        }
+    };
 
-    let (path, key) = path.rsplit_once('.').unwrap();
+    // the whole catalogue, which is grouped by category, even resources that were never obtained
+    let mut rows: Vec<_> = RESOURCES
+        .iter()
+        .map(|info| {
+            (
+                Some(info),
+                info.name.to_owned(),
+                resources.get(info.name).unwrap_or(0),
+            )
+        })
+        .collect();
+    let mut unknown: Vec<_> = resources
+        .iter()
+        .filter(|(name, _)| resource_info(name).is_none())
+        .map(|(name, amount)| (None, name.to_owned(), amount))
+        .collect();
+    unknown.sort_by(|(_, a, _), (_, b, _)| a.cmp(b));
+    rows.extend(unknown);
+
+    let mut changed = false;
+    let mut category = None;
+    for (info, name, mut amount) in rows {
+        let row_category = info.map(|info| info.category);
+        if category != Some(row_category) {
+            let heading = row_category.map_or("Other".to_owned(), |category| category.to_string());
+            ui.strong(heading);
+            ui.end_row();
+            category = Some(row_category);
+        }
 
-    let parent = path.split('.').fold(lua_state, |acc, segment| {
-        let table = acc.as_table_mut().expect("invalid path");
-        table.get_or_insert(segment, LuaValue::EMPTY_TABLE)
-    });
-    let number = parent
-        .as_table_mut()
-        .unwrap()
-        .get_or_insert(key, LuaValue::Number(0.0))
-        .as_number_mut()
-        .unwrap();
-
-    let mut edit = T::from_f64(*number);
-    let changed = ui.add(egui::DragValue::new(&mut edit)).changed();
-    if changed {
-        *number = T::to_f64(edit);
+        ui.label(info.map_or(name.as_str(), |info| info.display_name))
+            .on_hover_text(&name);
+        if ui.add(egui::DragValue::new(&mut amount)).changed() {
+            match resources.set(&name, amount) {
+                Ok(()) => changed = true,
+                Err(e) => show_error(ui, e.to_string()),
+            }
+        }
+        ui.end_row();
     }
-    ui.end_row();
 
     changed
 }
@@ -683,12 +771,12 @@ fn numeric<T: egui::emath::Numeric>(ui: &mut egui::Ui, label: &str, val: &mut T)
     ui.end_row();
     changed
 }
-/*fn checkbox(ui: &mut egui::Ui, label: &str, val: &mut bool) -> bool {
-ui.label(label);
-let changed = ui.checkbox(val, "").changed();
-ui.end_row();
-changed
-}*/
+fn checkbox(ui: &mut egui::Ui, label: &str, val: &mut bool) -> bool {
+    ui.label(label);
+    let changed = ui.checkbox(val, "").changed();
+    ui.end_row();
+    changed
+}
 
 fn time<T>(name: &str, f: impl FnOnce() -> T) -> T {
     let start = Instant::now();
@@ -1,64 +1,83 @@
 use egui::ahash::HashMap;
 use egui::{CollapsingHeader, Grid, TextEdit, Widget};
-use hades2::saves::{LuaTable, LuaValue};
-
-#[derive(Eq, PartialEq, Hash, Clone)]
-pub enum Pos {
-    Inline(u8, [u8; 15]),
-    Spilled(Vec<u16>),
+use hades2::saves::{LuaPath, LuaTable, LuaValue};
+
+/// Paths like `GameState.Resources.CardUpgradePoints`, parsed once when they are added
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Bookmarks {
+    pub items: Vec<(String, Result<LuaPath<'static>, String>)>,
+    /// Bookmark added or removed by a context menu, applied by [`Bookmarks::apply`]
+    toggled: Option<String>,
 }
 
-const _: () = assert!(std::mem::size_of::<Pos>() == 24);
+impl From<Vec<String>> for Bookmarks {
+    fn from(bookmarks: Vec<String>) -> Self {
+        let mut result = Bookmarks::default();
+        for bookmark in bookmarks {
+            result.add(bookmark);
+        }
+        result
+    }
+}
 
-impl Default for Pos {
-    fn default() -> Self {
-        Pos::Inline(0, [0; 15])
+impl From<Bookmarks> for Vec<String> {
+    fn from(bookmarks: Bookmarks) -> Self {
+        bookmarks.items.into_iter().map(|(text, _)| text).collect()
     }
 }
 
-impl Pos {
-    const MAX_INLINE_DEPTH: usize = 15;
-    const MAX_INLINE_CHILD: u16 = u8::MAX as u16;
-
-    #[must_use]
-    pub fn push(&self, val: u16) -> Pos {
-        match *self {
-            Pos::Inline(len, ref data) => {
-                let spill =
-                    len as usize > Self::MAX_INLINE_DEPTH - 1 || val > Self::MAX_INLINE_CHILD;
-
-                if spill {
-                    let mut new = Vec::with_capacity(len as usize + 1);
-                    new.extend(data.iter().copied().take(len as usize).map(u16::from));
-                    new.push(val);
-                    Pos::Spilled(new)
-                } else {
-                    let mut new_data = *data;
-                    new_data[len as usize] = val.try_into().unwrap();
-                    Pos::Inline(len + 1, new_data)
-                }
-            }
-            Pos::Spilled(ref vec) => {
-                let mut new = Vec::with_capacity(vec.len() + 1);
-                new.push(val);
-                Pos::Spilled(new)
-            }
+impl Bookmarks {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    pub fn contains(&self, text: &str) -> bool {
+        self.items.iter().any(|(bookmark, _)| bookmark == text)
+    }
+    fn add(&mut self, text: String) {
+        let path = text.parse::<LuaPath>().map_err(|e| e.to_string());
+        self.items.push((text, path));
+    }
+    /// Adds or removes a bookmark once the frame is done, so that the bookmarks can be shown meanwhile
+    pub fn toggle(&mut self, text: String) {
+        self.toggled = Some(text);
+    }
+    pub fn apply(&mut self) {
+        let Some(text) = self.toggled.take() else {
+            return;
+        };
+        match self
+            .items
+            .iter()
+            .position(|(bookmark, _)| *bookmark == text)
+        {
+            Some(i) => _ = self.items.remove(i),
+            None => self.add(text),
         }
     }
 }
 
+/// Shows `val` at `path`, which is restored to its original length before returning
 pub fn show_value(
     ui: &mut egui::Ui,
     val: &mut LuaValue,
-    pos: Pos,
-    nodes_visible: Option<&HashMap<Pos, bool>>,
+    path: &mut LuaPath<'static>,
+    nodes_visible: Option<&HashMap<LuaPath<'static>, bool>>,
+    bookmarks: &mut Bookmarks,
 ) -> bool {
+    let is_integer = val.is_integer();
     let response = match val {
         LuaValue::Nil => ui.label("Nil"),
         LuaValue::Bool(val) => ui.checkbox(val, ""),
         LuaValue::Number(val) => match ui.is_enabled() {
+            // integers are usually counters or IDs, which should stay integral
+            true if is_integer => ui.add(egui::DragValue::new(val).max_decimals(0)),
             true => ui.add(egui::DragValue::new(val)),
-            false => ui.label(val.to_string()),
+            false => ui.label(
+                LuaValue::Number(*val)
+                    .primitive_to_str()
+                    .unwrap_or_default(),
+            ),
         },
         LuaValue::String(val) => match ui.is_enabled() {
             true => TextEdit::singleline(val)
@@ -66,81 +85,96 @@ pub fn show_value(
                 .ui(ui),
             false => ui.label(val.as_ref()),
         },
-        LuaValue::Table(table) => return show_table(ui, table, pos, nodes_visible),
+        LuaValue::Table(table) => return show_table(ui, table, path, nodes_visible, bookmarks),
     };
     response.changed()
 }
 
+/// Adds a context menu to toggle whether the entry at `key` of the table at `path` is bookmarked.
+///
+/// The path of the entry is only built when the menu is open.
+fn bookmark_menu(
+    response: &egui::Response,
+    path: &LuaPath<'static>,
+    key: &LuaValue,
+    bookmarks: &mut Bookmarks,
+) {
+    response.context_menu(|ui| {
+        let path = path.join(key.clone().into_owned()).to_string();
+        let label = match bookmarks.contains(&path) {
+            true => "Remove bookmark",
+            false => "Bookmark",
+        };
+        if ui.button(label).clicked() {
+            bookmarks.toggle(path);
+            ui.close_menu();
+        }
+    });
+}
+
+type NodesVisible<'a> = Option<&'a HashMap<LuaPath<'static>, bool>>;
+
+/// Whether the entry at `key` is visible, and the visibility of its children.
+///
+/// The path of the entry is only built while filtering.
+fn test_visible<'a>(
+    path: &mut LuaPath<'static>,
+    key: &LuaValue,
+    nodes_visible: NodesVisible<'a>,
+) -> (bool, NodesVisible<'a>) {
+    let Some(visibles) = nodes_visible else {
+        return (true, None);
+    };
+    path.push(key.clone().into_owned());
+    let visible = visibles.get(path).copied();
+    path.pop();
+    match visible {
+        Some(true) => (true, None),
+        Some(false) => (true, nodes_visible),
+        None => (false, nodes_visible),
+    }
+}
+
 fn show_table(
     ui: &mut egui::Ui,
     table: &mut LuaTable,
-    pos: Pos,
-    nodes_visible: Option<&HashMap<Pos, bool>>,
+    path: &mut LuaPath<'static>,
+    nodes_visible: NodesVisible<'_>,
+    bookmarks: &mut Bookmarks,
 ) -> bool {
-    fn test_visible<'a>(
-        pos: &Pos,
-        nodes_visible: Option<&'a HashMap<Pos, bool>>,
-    ) -> (bool, Option<&'a HashMap<Pos, bool>>) {
-        let (is_visible, all_children_visible) = match nodes_visible {
-            Some(visibles) => match visibles.get(pos) {
-                Some(true) => (true, true),
-                Some(false) => (true, false),
-                None => (false, false),
-            },
-            None => (true, true),
-        };
-        let nodes_visible_children = match all_children_visible {
-            true => None,
-            false => nodes_visible,
-        };
-        (is_visible, nodes_visible_children)
-    }
-
     let mut changed = false;
 
-    let entries = table
-        .iter_mut()
-        .enumerate()
-        .map(|(i, entry)| {
-            let new_pos = pos.push(i.try_into().unwrap());
-            (new_pos, entry)
-        })
-        .collect::<Vec<_>>();
-
-    let has_primitives = entries.iter().any(|(pos, (_, val))| {
-        val.is_primitive() && nodes_visible.map_or(true, |v| v.contains_key(&pos))
+    let primitives = table
+        .iter()
+        .take_while(|(_, val)| val.is_primitive())
+        .count();
+    let has_primitives = table.iter().take(primitives).any(|(key, _)| {
+        let (is_visible, _) = test_visible(path, key, nodes_visible);
+        is_visible
     });
 
-    let mut entries = entries.into_iter().peekable();
+    let (primitive_entries, table_entries) = table.0.split_at_mut(primitives);
 
     if has_primitives {
-        Grid::new(egui::Id::new(&pos)).show(ui, |ui| {
-            while let Some(&mut (ref pos, (key, val))) = entries.peek_mut() {
-                if !val.is_primitive() {
-                    break;
-                }
-
-                let (is_visible, nodevis_children) = test_visible(pos, nodes_visible);
-
+        Grid::new(egui::Id::new(&*path)).show(ui, |ui| {
+            for (key, val) in primitive_entries {
+                let (is_visible, nodevis_children) = test_visible(path, key, nodes_visible);
                 if !is_visible {
-                    entries.next();
                     continue;
                 }
 
-                ui.add_enabled_ui(false, |ui| {
-                    show_value(ui, key, pos.clone(), nodevis_children);
-                });
+                let name = key.primitive_to_str().unwrap_or_default();
+                let response = egui::Label::new(name).sense(egui::Sense::click()).ui(ui);
+                bookmark_menu(&response, path, key, bookmarks);
 
-                changed |= show_value(ui, val, pos.clone(), nodevis_children);
+                changed |= show_value(ui, val, path, nodevis_children, bookmarks);
                 ui.end_row();
-
-                entries.next();
             }
         });
     }
 
-    for (pos, (key, val)) in entries {
-        let (is_visible, nodevis_children) = test_visible(&pos, nodes_visible);
+    for (key, val) in table_entries {
+        let (is_visible, nodevis_children) = test_visible(path, key, nodes_visible);
         if !is_visible {
             continue;
         }
@@ -150,9 +184,12 @@ fn show_table(
         };
         let name = key.primitive_to_str().unwrap_or_default();
 
-        CollapsingHeader::new(name).show(ui, |ui| {
-            changed |= show_table(ui, inner, pos.clone(), nodevis_children);
+        let response = CollapsingHeader::new(name).show(ui, |ui| {
+            path.push(key.clone().into_owned());
+            changed |= show_table(ui, inner, path, nodevis_children, bookmarks);
+            path.pop();
         });
+        bookmark_menu(&response.header_response, path, key, bookmarks);
     }
 
     changed
@@ -0,0 +1,191 @@
+//! Size analysis of lua states, to find out which parts of a savefile take up the most space.
+
+use std::borrow::Cow;
+
+use crate::saves::{LuaPath, LuaValue};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtreeStats<'a> {
+    pub path: LuaPath<'a>,
+    /// Size of the table in the uncompressed luabins encoding
+    pub encoded_bytes: usize,
+    /// Part of `encoded_bytes` not taken up by nested tables
+    pub own_bytes: usize,
+    /// Number of values in the table, including keys and the table itself
+    pub nodes: usize,
+    /// Number of nested table levels, `1` for a table containing only primitives
+    pub depth: usize,
+    /// Total length of all strings in the table, including keys
+    pub string_bytes: usize,
+}
+
+/// Computes [`SubtreeStats`] for every table in `root`, in depth-first order.
+pub fn subtree_stats<'a>(root: &'a LuaValue<'_>) -> Vec<SubtreeStats<'a>> {
+    let mut stats = Vec::new();
+    let mut path = LuaPath::new();
+    visit(root, &mut path, &mut stats);
+    stats
+}
+
+/// Returns the `n` tables with the largest [`own_bytes`](SubtreeStats::own_bytes).
+///
+/// Ranking by the size without nested tables finds the records which grow large themselves,
+/// instead of the tables which merely contain them.
+pub fn heaviest_subtrees<'a>(root: &'a LuaValue<'_>, n: usize) -> Vec<SubtreeStats<'a>> {
+    let mut stats = subtree_stats(root);
+    stats.sort_by_key(|stats| std::cmp::Reverse(stats.own_bytes));
+    stats.truncate(n);
+    stats
+}
+
+/// Approximate number of bytes `value` allocates on the heap, not including `value` itself.
+pub fn heap_bytes(value: &LuaValue<'_>) -> usize {
+    match value {
+        LuaValue::Nil | LuaValue::Bool(_) | LuaValue::Number(_) => 0,
+        LuaValue::String(Cow::Borrowed(_)) => 0,
+        LuaValue::String(Cow::Owned(val)) => val.capacity(),
+        LuaValue::Table(table) => {
+            table.0.capacity() * size_of::<(LuaValue, LuaValue)>()
+                + table
+                    .iter()
+                    .map(|(key, val)| heap_bytes(key) + heap_bytes(val))
+                    .sum::<usize>()
+        }
+    }
+}
+
+struct Totals {
+    encoded_bytes: usize,
+    nodes: usize,
+    depth: usize,
+    string_bytes: usize,
+}
+
+fn visit<'a>(
+    value: &'a LuaValue<'_>,
+    path: &mut LuaPath<'a>,
+    stats: &mut Vec<SubtreeStats<'a>>,
+) -> Totals {
+    // see `luabins::write::save_value`
+    let primitive = |encoded_bytes, string_bytes| Totals {
+        encoded_bytes,
+        nodes: 1,
+        depth: 0,
+        string_bytes,
+    };
+    let table = match value {
+        LuaValue::Nil | LuaValue::Bool(_) => return primitive(1, 0),
+        LuaValue::Number(_) => return primitive(1 + 8, 0),
+        LuaValue::String(val) => return primitive(1 + 4 + val.len(), val.len()),
+        LuaValue::Table(table) => table,
+    };
+
+    let index = stats.len();
+    stats.push(SubtreeStats {
+        path: path.clone(),
+        encoded_bytes: 0,
+        own_bytes: 0,
+        nodes: 0,
+        depth: 0,
+        string_bytes: 0,
+    });
+
+    let mut totals = Totals {
+        encoded_bytes: 1 + 4 + 4,
+        nodes: 1,
+        depth: 0,
+        string_bytes: 0,
+    };
+    let mut nested_bytes = 0;
+    for (key, val) in table.iter() {
+        path.push(key.borrowed());
+        let key = visit(key, path, stats);
+        let val = visit(val, path, stats);
+        path.pop();
+
+        for child in [key, val] {
+            if child.depth > 0 {
+                nested_bytes += child.encoded_bytes;
+            }
+            totals.encoded_bytes += child.encoded_bytes;
+            totals.nodes += child.nodes;
+            totals.depth = totals.depth.max(child.depth);
+            totals.string_bytes += child.string_bytes;
+        }
+    }
+    totals.depth += 1;
+
+    let entry = &mut stats[index];
+    entry.encoded_bytes = totals.encoded_bytes;
+    entry.own_bytes = totals.encoded_bytes - nested_bytes;
+    entry.nodes = totals.nodes;
+    entry.depth = totals.depth;
+    entry.string_bytes = totals.string_bytes;
+
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::luabins::write::save_value;
+    use crate::saves::{LuaValue, Savefile, read_luatext};
+
+    const TEST_PROFILE_V18: &[u8] = include_bytes!("../../../testdata/Profile.v18.sav").as_slice();
+
+    #[test]
+    fn stats_match_encoding() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let stats = super::subtree_stats(&lua_state);
+
+        for entry in stats.iter().step_by(97) {
+            let value = entry.path.resolve(&lua_state).unwrap();
+
+            let mut encoded = Vec::new();
+            save_value(&mut encoded, value);
+            assert_eq!(entry.encoded_bytes, encoded.len(), "{}", entry.path);
+            assert_eq!(entry.nodes, value.count(true, &mut |_| true));
+        }
+
+        let heaviest = super::heaviest_subtrees(&lua_state, 3);
+        assert!(heaviest[0].own_bytes >= heaviest[1].own_bytes);
+        assert!(
+            heaviest
+                .iter()
+                .all(|entry| entry.own_bytes <= entry.encoded_bytes)
+        );
+    }
+
+    #[test]
+    fn heaviest_siblings() {
+        let value: LuaValue = read_luatext(
+            r#"{ parent = {
+                a = { "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa" },
+                b = { "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbb", "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbb" },
+                c = 1,
+            }, d = 2 }"#,
+        )
+        .unwrap();
+        let heaviest = super::heaviest_subtrees(&value, 2);
+
+        let mut paths: Vec<_> = heaviest.iter().map(|s| s.path.to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, ["parent.a", "parent.b"]);
+        let stats = super::subtree_stats(&value);
+        assert_eq!(
+            stats[1].own_bytes,
+            stats[1].encoded_bytes - stats[2].encoded_bytes - stats[3].encoded_bytes
+        );
+    }
+
+    #[test]
+    fn stats_nested() {
+        let value: LuaValue = read_luatext(r#"{ a = { b = { "xy" } }, c = 1 }"#).unwrap();
+        let stats = super::subtree_stats(&value);
+
+        let paths: Vec<_> = stats.iter().map(|s| s.path.to_string()).collect();
+        assert_eq!(paths, ["", "a", "a.b"]);
+        assert_eq!(stats[0].depth, 3);
+        assert_eq!(stats[0].string_bytes, "acbxy".len());
+        assert_eq!(stats[2].nodes, 3);
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Result, anyhow};
-use hades2::saves::Savefile;
+use hades2::diff::{Change, diff};
+use hades2::saves::{LuaValue, Savefile, write_luatext};
 
 fn main() -> Result<()> {
     let mut args = std::env::args().skip(1);
@@ -16,13 +17,21 @@ fn main() -> Result<()> {
     let data_b = std::fs::read(&path_b)?;
     let (_savefile_b, lua_b) = Savefile::parse(&data_b)?;
 
-    let tmp = std::env::temp_dir();
-    let out_a = tmp.join("a.txt");
-    std::fs::write(&out_a, format!("{lua_a:#?}"))?;
-    let out_b = tmp.join("b.txt");
-    std::fs::write(&out_b, format!("{lua_b:#?}"))?;
-
-    println!("code --diff {} {}", out_a.display(), out_b.display());
+    for change in diff(&lua_a, &lua_b) {
+        match change {
+            Change::Added(path, new) => println!("+ {path} = {}", luatext(new)),
+            Change::Removed(path, old) => println!("- {path} = {}", luatext(old)),
+            Change::Changed { path, old, new } => {
+                println!("~ {path} = {} -> {}", luatext(old), luatext(new))
+            }
+        }
+    }
 
     Ok(())
 }
+
+fn luatext(value: &LuaValue) -> String {
+    let mut out = String::new();
+    write_luatext(&mut out, value);
+    out
+}
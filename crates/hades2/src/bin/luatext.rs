@@ -0,0 +1,35 @@
+use anyhow::{Result, anyhow, bail};
+use hades2::saves::{Savefile, read_luatext, write_luatext};
+
+const USAGE: &str =
+    "Usage: luatext export <save> <out.lua> | luatext import <save> <in.lua> <out.sav>";
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut arg = || args.next().ok_or_else(|| anyhow!(USAGE));
+
+    match arg()?.as_str() {
+        "export" => {
+            let data = std::fs::read(arg()?)?;
+            let (_savefile, lua) = Savefile::parse(&data)?;
+
+            let mut text = String::new();
+            write_luatext(&mut text, &lua);
+            std::fs::write(arg()?, text)?;
+        }
+        "import" => {
+            let data = std::fs::read(arg()?)?;
+            let savefile = Savefile::parse_header_only(&data)?;
+
+            let lua = read_luatext(&std::fs::read_to_string(arg()?)?)?;
+
+            let mut out = Vec::new();
+            savefile.serialize(&mut out, &lua)?;
+            Savefile::parse(&out)?;
+            std::fs::write(arg()?, out)?;
+        }
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anyhow::{Result, anyhow};
+use hades2::analysis::heaviest_subtrees;
+use hades2::saves::Savefile;
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or_else(|| anyhow!("Expected path to file as first argument"))?;
+    let n = match args.next() {
+        Some(n) => n.parse()?,
+        None => 30,
+    };
+
+    let data = std::fs::read(&path)?;
+    let (_savefile, lua) = Savefile::parse(&data)?;
+
+    println!(
+        "{:>10} {:>10} {:>8} {:>5} {:>10}  path",
+        "own bytes", "bytes", "nodes", "depth", "strings"
+    );
+    for stats in heaviest_subtrees(&lua, n) {
+        println!(
+            "{:>10} {:>10} {:>8} {:>5} {:>10}  {}",
+            stats.own_bytes,
+            stats.encoded_bytes,
+            stats.nodes,
+            stats.depth,
+            stats.string_bytes,
+            stats.path
+        );
+    }
+
+    Ok(())
+}
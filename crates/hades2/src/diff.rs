@@ -0,0 +1,124 @@
+//! Structural differences between two lua states, addressed by [`LuaPath`].
+
+use crate::parser::canonical::sorted_entries;
+use crate::saves::{LuaPath, LuaValue};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    Added(LuaPath<'a>, &'a LuaValue<'a>),
+    Removed(LuaPath<'a>, &'a LuaValue<'a>),
+    /// The value changed, or one side is a table and the other one isn't
+    Changed {
+        path: LuaPath<'a>,
+        old: &'a LuaValue<'a>,
+        new: &'a LuaValue<'a>,
+    },
+}
+
+impl<'a> Change<'a> {
+    pub fn path(&self) -> &LuaPath<'a> {
+        match self {
+            Change::Added(path, _) | Change::Removed(path, _) => path,
+            Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Compares `old` and `new`, descending into tables present on both sides.
+///
+/// Entries are matched by key, so the order of entries in a table doesn't matter.
+/// Values are compared with [`LuaValue::canonical_eq`], so a NaN is equal to itself.
+/// Changes are returned in the entry order of `old`, followed by entries only present in `new`.
+pub fn diff<'a>(old: &'a LuaValue<'a>, new: &'a LuaValue<'a>) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    diff_inner(old, new, &mut LuaPath::new(), &mut changes);
+    changes
+}
+
+fn diff_inner<'a>(
+    old: &'a LuaValue<'a>,
+    new: &'a LuaValue<'a>,
+    path: &mut LuaPath<'a>,
+    changes: &mut Vec<Change<'a>>,
+) {
+    let (LuaValue::Table(old_table), LuaValue::Table(new_table)) = (old, new) else {
+        if !old.canonical_eq(new) {
+            changes.push(Change::Changed {
+                path: path.clone(),
+                old,
+                new,
+            });
+        }
+        return;
+    };
+
+    // matching keys with binary searches instead of `find` keeps large tables like `GameState` fast
+    let (old_sorted, new_sorted) = (sorted_entries(old_table), sorted_entries(new_table));
+    let find = |sorted: &[&'a (LuaValue<'a>, LuaValue<'a>)], key: &LuaValue| {
+        let index = sorted
+            .binary_search_by(|(other, _)| other.canonical_cmp(key))
+            .ok()?;
+        Some(&sorted[index].1)
+    };
+
+    for (key, old_val) in old_table.iter() {
+        path.push(key.borrowed());
+        match find(&new_sorted, key) {
+            Some(new_val) => diff_inner(old_val, new_val, path, changes),
+            None => changes.push(Change::Removed(path.clone(), old_val)),
+        }
+        path.pop();
+    }
+    for (key, new_val) in new_table.iter() {
+        if find(&old_sorted, key).is_none() {
+            changes.push(Change::Added(path.join(key.borrowed()), new_val));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Change;
+    use crate::saves::{LuaValue, Savefile, read_luatext};
+
+    const TEST_PROFILE_V18: &[u8] = include_bytes!("../../../testdata/Profile.v18.sav").as_slice();
+
+    #[test]
+    fn diff_by_key() {
+        let old = read_luatext(r#"{ a = { b = 1, c = 2 }, d = "x", e = { 1 } }"#).unwrap();
+        let new = read_luatext(r#"{ a = { c = 2, b = 3, f = true }, d = { }, g = 0 }"#).unwrap();
+
+        let changes: Vec<_> = super::diff(&old, &new)
+            .iter()
+            .map(|change| {
+                let kind = match change {
+                    Change::Added(..) => "+",
+                    Change::Removed(..) => "-",
+                    Change::Changed { .. } => "~",
+                };
+                format!("{kind}{}", change.path())
+            })
+            .collect();
+        assert_eq!(changes, ["~d", "~a.b", "+a.f", "-e", "+g"]);
+
+        let nan = read_luatext("{ a = 0/0, b = 1 }").unwrap();
+        assert_eq!(super::diff(&nan, &nan), []);
+    }
+
+    #[test]
+    fn diff_resolves() {
+        let (_, old_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut new_state = old_state.clone();
+        *new_state
+            .get_path_mut("GameState.Resources.CardUpgradePoints")
+            .unwrap() = LuaValue::Number(12345.0);
+
+        let changes = super::diff(&old_state, &new_state);
+        let [Change::Changed { path, old, new }] = changes.as_slice() else {
+            panic!("{changes:?}");
+        };
+        assert_eq!(path.to_string(), "GameState.Resources.CardUpgradePoints");
+        assert_eq!(path.resolve(&old_state), Some(*old));
+        assert_eq!(path.resolve(&new_state), Some(*new));
+    }
+}
@@ -0,0 +1,306 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, check_table, entries, insert, table, table_mut};
+use crate::saves::{LuaValue, Savefile};
+
+/// Highest level an arcana card can be upgraded to
+pub const MAX_CARD_LEVEL: u32 = 3;
+
+/// Entry of the [`CARDS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardInfo {
+    /// Key in `GameState.MetaUpgradeState`
+    pub name: &'static str,
+    /// Title of the Arcana card, like `The Sorceress`
+    pub display_name: &'static str,
+    /// Grasp needed to equip the card
+    pub cost: u32,
+}
+
+const fn card(name: &'static str, display_name: &'static str, cost: u32) -> CardInfo {
+    CardInfo {
+        name,
+        display_name,
+        cost,
+    }
+}
+
+/// The 25 arcana cards, in the order of the game's layout from I to XXV
+#[rustfmt::skip]
+pub const CARDS: &[CardInfo] = &[
+    card("ChanneledCast", "The Sorceress", 1),
+    card("HealthRegen", "The Wayward Son", 1),
+    card("LowManaDamageBonus", "The Huntress", 2),
+    card("CastCount", "Eos", 3),
+    card("SorceryRegenUpgrade", "The Moon", 2),
+    card("CastBuff", "The Furies", 1),
+    card("BonusHealth", "Persistence", 2),
+    card("BonusDodge", "The Messenger", 1),
+    card("ManaOverTime", "The Unseen", 2),
+    card("MagicCrit", "Night", 3),
+    card("SprintShield", "The Swift Runner", 1),
+    card("LastStand", "Death", 3),
+    card("MaxHealthPerRoom", "The Centaur", 3),
+    card("StatusVulnerability", "Origination", 5),
+    card("ChanneledBlock", "The Lovers", 2),
+    card("DoorReroll", "The Enchantress", 1),
+    card("StartingGold", "The Boatman", 1),
+    card("MetaToRunUpgrade", "The Artificer", 2),
+    card("RarityBoost", "Excellence", 2),
+    card("BonusRarity", "The Queen", 4),
+    card("TradeOff", "The Fates", 0),
+    card("ScreenReroll", "The Champions", 2),
+    card("LowHealthBonus", "Strength", 3),
+    card("EpicRarityBoost", "Divinity", 5),
+    card("CardDraw", "Judgment", 0),
+];
+
+/// Looks up a card in the [`CARDS`] catalogue by its key in `GameState.MetaUpgradeState`
+pub fn card_info(name: &str) -> Option<&'static CardInfo> {
+    CARDS.iter().find(|info| info.name == name)
+}
+
+/// State of a single card in `GameState.MetaUpgradeState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardState<'v> {
+    pub name: &'v str,
+    pub unlocked: bool,
+    /// Starts at 1, even for locked cards
+    pub level: u32,
+    pub equipped: bool,
+}
+
+impl CardState<'_> {
+    pub fn upgraded(&self) -> bool {
+        self.level > 1
+    }
+    /// Grasp cost from the [`CARDS`] catalogue
+    pub fn cost(&self) -> Option<u32> {
+        card_info(self.name).map(|info| info.cost)
+    }
+}
+
+fn card_state<'v>(name: &'v str, state: &'v LuaValue<'_>) -> CardState<'v> {
+    let flag = |key| state.get(key).and_then(LuaValue::as_bool) == Some(true);
+    CardState {
+        name,
+        unlocked: flag("Unlocked"),
+        level: state.get("Level").and_then(LuaValue::as_u32).unwrap_or(1),
+        equipped: flag("Equipped"),
+    }
+}
+
+/// View over the arcana cards in `GameState.MetaUpgradeState`, and the grasp needed to equip them.
+///
+/// The game caches the number of unlocked and fully upgraded cards in `GameState`,
+/// which is kept up to date by every operation.
+#[derive(Debug, Clone, Copy)]
+pub struct Cards<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Cards<V> {
+    /// All cards, skipping the game's internal `Base*MetaUpgrade` entries
+    pub fn iter<'v>(&'v self) -> impl Iterator<Item = CardState<'v>>
+    where
+        'a: 'v,
+    {
+        entries(table(&self.value, "MetaUpgradeState"))
+            .filter(|(_, state)| state.as_table().is_some())
+            .filter_map(|(name, state)| Some((name.as_str()?, state)))
+            .filter(|(name, _)| !(name.starts_with("Base") && name.ends_with("MetaUpgrade")))
+            .map(|(name, state)| card_state(name, state))
+    }
+    pub fn get<'v>(&'v self, name: &str) -> Option<CardState<'v>>
+    where
+        'a: 'v,
+    {
+        self.iter().find(|card| card.name == name)
+    }
+
+    /// Grasp, the maximum total cost of equipped cards, which the header stores as well
+    pub fn grasp(&self) -> Option<u32> {
+        self.value.get("MaxMetaUpgradeCostCache")?.as_u32()
+    }
+    /// Total cost of the equipped cards, where cards missing from the catalogue cost nothing
+    pub fn equipped_cost(&self) -> u32 {
+        self.iter()
+            .filter(|card| card.equipped)
+            .filter_map(|card| card.cost())
+            .sum()
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Cards<V> {
+    /// Fails like [`Cards::card_mut`] would, without inserting anything
+    fn check_card(&self, name: &str) -> Result<()> {
+        check_table(&self.value, "MetaUpgradeState")?;
+        let states = table(&self.value, "MetaUpgradeState");
+        if states.get(name).is_none() && card_info(name).is_none() {
+            return Err(Error::UnknownCard(name.to_owned()));
+        }
+        check_table(states, name)?;
+        check_table(table(states, name), "AdjacencyBonuses")
+    }
+    fn card_mut(&mut self, name: &str) -> Result<&mut LuaValue<'a>> {
+        self.check_card(name)?;
+        let states = table_mut(&mut self.value, "MetaUpgradeState")?;
+        let state = table_mut(states, name)?;
+        if state.get("Level").is_none() {
+            insert(state, "Level", LuaValue::Number(1.0));
+        }
+        table_mut(state, "AdjacencyBonuses")?;
+        Ok(state)
+    }
+
+    fn update_counts(&mut self) {
+        let (unlocked, max_level) =
+            self.iter()
+                .fold((0u32, 0u32), |(unlocked, max_level), card| {
+                    (
+                        unlocked + u32::from(card.unlocked),
+                        max_level + u32::from(card.unlocked && card.level >= MAX_CARD_LEVEL),
+                    )
+                });
+        insert(
+            &mut self.value,
+            "MetaUpgradeUnlockedCountCache",
+            LuaValue::Number(unlocked.into()),
+        );
+        insert(
+            &mut self.value,
+            "MetaUpgradeMaxLevelCountCache",
+            LuaValue::Number(max_level.into()),
+        );
+    }
+
+    /// Unlocks a card, which needs to be in the catalogue or the savefile
+    pub fn unlock(&mut self, name: &str) -> Result<()> {
+        let state = self.card_mut(name)?;
+        insert(state, "Unlocked", LuaValue::Bool(true));
+        self.update_counts();
+        Ok(())
+    }
+    /// Unlocks every card of the [`CARDS`] catalogue
+    pub fn unlock_all(&mut self) -> Result<()> {
+        for info in CARDS {
+            self.check_card(info.name)?;
+        }
+        for info in CARDS {
+            let state = self.card_mut(info.name)?;
+            insert(state, "Unlocked", LuaValue::Bool(true));
+        }
+        self.update_counts();
+        Ok(())
+    }
+
+    /// Sets the level of an unlocked card, between 1 and [`MAX_CARD_LEVEL`]
+    pub fn set_level(&mut self, name: &str, level: u32) -> Result<()> {
+        if !(1..=MAX_CARD_LEVEL).contains(&level) {
+            return Err(Error::InvalidCardLevel {
+                name: name.to_owned(),
+                level,
+            });
+        }
+        if !self.get(name).is_some_and(|card| card.unlocked) {
+            return Err(Error::CardLocked(name.to_owned()));
+        }
+        let state = self.card_mut(name)?;
+        insert(state, "Level", LuaValue::Number(level.into()));
+        self.update_counts();
+        Ok(())
+    }
+    /// Raises the level of an unlocked card by one
+    pub fn upgrade(&mut self, name: &str) -> Result<u32> {
+        let level = self.get(name).map_or(1, |card| card.level) + 1;
+        self.set_level(name, level)?;
+        Ok(level)
+    }
+
+    /// Sets the grasp both in the lua state and the header of `savefile`.
+    ///
+    /// Fails if the equipped cards would cost more.
+    pub fn set_grasp(&mut self, savefile: &mut Savefile, grasp: u32) -> Result<()> {
+        let equipped = self.equipped_cost();
+        if grasp < equipped {
+            return Err(Error::GraspTooLow { grasp, equipped });
+        }
+        insert(
+            &mut self.value,
+            "MaxMetaUpgradeCostCache",
+            LuaValue::Number(grasp.into()),
+        );
+        savefile.grasp = grasp;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CARDS, MAX_CARD_LEVEL, card_info};
+    use crate::game::testdata::TEST_PROFILE_V18;
+    use crate::game::{Error, State};
+    use crate::saves::{LuaValue, Savefile};
+
+    #[test]
+    fn read_cards() {
+        let (savefile, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        let cards = state.cards();
+
+        assert_eq!(cards.iter().count(), CARDS.len());
+        assert!(cards.iter().all(|card| card_info(card.name).is_some()));
+        assert_eq!(cards.iter().filter(|card| card.unlocked).count(), 14);
+
+        let death = cards.get("LastStand").unwrap();
+        assert!(death.unlocked && death.equipped && death.upgraded());
+        assert_eq!(death.cost(), Some(3));
+        assert!(!cards.get("CardDraw").unwrap().unlocked);
+
+        assert_eq!(cards.grasp(), Some(savefile.grasp));
+        let cached_cost = lua_state.get_path("GameState.MetaUpgradeCostCache");
+        assert_eq!(
+            cached_cost.and_then(LuaValue::as_u32),
+            Some(cards.equipped_cost())
+        );
+    }
+
+    #[test]
+    fn unlock_upgrade() {
+        let (mut savefile, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut cards = state.cards_mut().unwrap();
+
+        assert!(matches!(
+            cards.upgrade("CardDraw"),
+            Err(Error::CardLocked(_))
+        ));
+        cards.unlock_all().unwrap();
+        assert_eq!(cards.upgrade("CardDraw").unwrap(), 2);
+        assert_eq!(cards.upgrade("LastStand").unwrap(), MAX_CARD_LEVEL);
+        assert!(matches!(
+            cards.upgrade("LastStand"),
+            Err(Error::InvalidCardLevel { level: 4, .. })
+        ));
+        assert!(matches!(
+            cards.unlock("NotACard"),
+            Err(Error::UnknownCard(_))
+        ));
+
+        assert!(matches!(
+            cards.set_grasp(&mut savefile, 10),
+            Err(Error::GraspTooLow { equipped: 17, .. })
+        ));
+        cards.set_grasp(&mut savefile, 30).unwrap();
+        assert_eq!(savefile.grasp, 30);
+
+        let count = |key| lua_state.get("GameState").unwrap().get(key)?.as_u32();
+        assert_eq!(count("MetaUpgradeUnlockedCountCache"), Some(25));
+        assert_eq!(count("MetaUpgradeMaxLevelCountCache"), Some(1));
+        assert_eq!(count("MaxMetaUpgradeCostCache"), Some(30));
+        let card = lua_state
+            .get_path("GameState.MetaUpgradeState.CardDraw")
+            .unwrap();
+        assert!(card.get("AdjacencyBonuses").is_some());
+    }
+}
@@ -0,0 +1,222 @@
+//! Checks of the savefile header against the lua state it summarizes.
+//!
+//! The header is what the game shows in the profile picker, so editing only the lua state
+//! (or only the header) shows one thing there and another in the game.
+
+use std::fmt;
+
+use super::State;
+use crate::saves::{LuaValue, Savefile};
+
+/// Fields of the [`Savefile`] header which repeat something from the lua state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeaderField {
+    /// `GameState.CompletedRunsCache`
+    Runs,
+    /// `GameState.MaxMetaUpgradeCostCache`
+    Grasp,
+    /// `GameState.SpentShrinePointsCache`, the fear of the active vows
+    ActiveShrinePoints,
+    /// `GameState.Flags.EasyMode`, set while God Mode is active
+    EasyMode,
+    /// `GameState.Flags.HardMode`
+    HardMode,
+    /// `CurrentHubRoom.SaveProfileLocationText`
+    Location,
+    /// `CurrentHubRoom.Name`, or `CurrentRun.CurrentRoom.Name` outside of the hub
+    CurrentMapName,
+}
+
+impl HeaderField {
+    pub const ALL: [HeaderField; 7] = [
+        HeaderField::Runs,
+        HeaderField::Grasp,
+        HeaderField::ActiveShrinePoints,
+        HeaderField::EasyMode,
+        HeaderField::HardMode,
+        HeaderField::Location,
+        HeaderField::CurrentMapName,
+    ];
+}
+
+impl fmt::Display for HeaderField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HeaderField::Runs => "runs",
+            HeaderField::Grasp => "grasp",
+            HeaderField::ActiveShrinePoints => "active_shrine_points",
+            HeaderField::EasyMode => "easy_mode",
+            HeaderField::HardMode => "hard_mode",
+            HeaderField::Location => "location",
+            HeaderField::CurrentMapName => "current_map_name",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderValue {
+    Number(u32),
+    Bool(bool),
+    String(String),
+}
+
+impl fmt::Display for HeaderValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderValue::Number(val) => write!(f, "{val}"),
+            HeaderValue::Bool(val) => write!(f, "{val}"),
+            HeaderValue::String(val) => write!(f, "{val:?}"),
+        }
+    }
+}
+
+/// A header field which disagrees with the lua state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inconsistency {
+    pub field: HeaderField,
+    pub header: HeaderValue,
+    pub state: HeaderValue,
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "header `{}` is {}, but the lua state has {}",
+            self.field, self.header, self.state
+        )
+    }
+}
+
+fn header_value(savefile: &Savefile, field: HeaderField) -> HeaderValue {
+    match field {
+        HeaderField::Runs => HeaderValue::Number(savefile.runs),
+        HeaderField::Grasp => HeaderValue::Number(savefile.grasp),
+        HeaderField::ActiveShrinePoints => HeaderValue::Number(savefile.active_shrine_points),
+        HeaderField::EasyMode => HeaderValue::Bool(savefile.easy_mode),
+        HeaderField::HardMode => HeaderValue::Bool(savefile.hard_mode),
+        HeaderField::Location => HeaderValue::String(savefile.location.clone()),
+        HeaderField::CurrentMapName => HeaderValue::String(savefile.current_map_name.clone()),
+    }
+}
+
+/// The value the lua state implies for `field`, or `None` if it doesn't record it
+fn state_value(lua_state: &LuaValue, field: HeaderField) -> Option<HeaderValue> {
+    let state = State::new(lua_state);
+    let game_state = lua_state.get("GameState");
+    let number = |key| Some(HeaderValue::Number(game_state?.get(key)?.as_u32()?));
+    let flag = |key| HeaderValue::Bool(state.settings().flags().contains(key));
+    let hub_room = lua_state.get("CurrentHubRoom");
+    let string = |val: &LuaValue| Some(HeaderValue::String(val.as_str()?.to_owned()));
+
+    match field {
+        HeaderField::Runs => number("CompletedRunsCache"),
+        HeaderField::Grasp => number("MaxMetaUpgradeCostCache"),
+        HeaderField::ActiveShrinePoints => number("SpentShrinePointsCache"),
+        HeaderField::EasyMode => Some(flag("EasyMode")),
+        HeaderField::HardMode => Some(flag("HardMode")),
+        HeaderField::Location => string(hub_room?.get("SaveProfileLocationText")?),
+        HeaderField::CurrentMapName => match hub_room {
+            Some(hub_room) => string(hub_room.get("Name")?),
+            None => string(lua_state.get_path("CurrentRun.CurrentRoom.Name")?),
+        },
+    }
+}
+
+/// Lists the header fields which disagree with the lua state.
+///
+/// Fields the lua state doesn't record are not checked. The active shrine points are compared with
+/// `SpentShrinePointsCache`, the game's own total. [`Vows::validate`](super::Vows::validate) checks
+/// them against the fear of the vows instead, using this crate's catalogue.
+pub fn check_consistency(savefile: &Savefile, lua_state: &LuaValue) -> Vec<Inconsistency> {
+    HeaderField::ALL
+        .into_iter()
+        .filter_map(|field| {
+            let state = state_value(lua_state, field)?;
+            let header = header_value(savefile, field);
+            (header != state).then_some(Inconsistency {
+                field,
+                header,
+                state,
+            })
+        })
+        .collect()
+}
+
+/// Overwrites the header fields which disagree with the lua state, and returns what was changed
+pub fn sync_header_from_state(savefile: &mut Savefile, lua_state: &LuaValue) -> Vec<Inconsistency> {
+    let inconsistencies = check_consistency(savefile, lua_state);
+    for inconsistency in &inconsistencies {
+        match (inconsistency.field, &inconsistency.state) {
+            (HeaderField::Runs, &HeaderValue::Number(val)) => savefile.runs = val,
+            (HeaderField::Grasp, &HeaderValue::Number(val)) => savefile.grasp = val,
+            (HeaderField::ActiveShrinePoints, &HeaderValue::Number(val)) => {
+                savefile.active_shrine_points = val
+            }
+            (HeaderField::EasyMode, &HeaderValue::Bool(val)) => savefile.easy_mode = val,
+            (HeaderField::HardMode, &HeaderValue::Bool(val)) => savefile.hard_mode = val,
+            (HeaderField::Location, HeaderValue::String(val)) => savefile.location = val.clone(),
+            (HeaderField::CurrentMapName, HeaderValue::String(val)) => {
+                savefile.current_map_name = val.clone()
+            }
+            _ => unreachable!("state values have the type of their field"),
+        }
+    }
+    inconsistencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeaderField, HeaderValue, check_consistency, sync_header_from_state};
+    use crate::game::State;
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::saves::Savefile;
+
+    #[test]
+    fn consistent_saves() {
+        for data in [TEST_PROFILE_V17, TEST_PROFILE_V18] {
+            let (savefile, lua_state) = Savefile::parse(data).unwrap();
+            assert_eq!(check_consistency(&savefile, &lua_state), []);
+        }
+    }
+
+    #[test]
+    fn sync_header() {
+        let (original, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut savefile = original.clone();
+        savefile.runs = 100;
+        savefile.location = "Location_Elsewhere".into();
+        let mut state = State::new(&mut lua_state);
+        state
+            .settings_mut()
+            .unwrap()
+            .flags_mut()
+            .unwrap()
+            .insert("HardMode");
+
+        let inconsistencies = check_consistency(&savefile, &lua_state);
+        let fields: Vec<_> = inconsistencies.iter().map(|i| i.field).collect();
+        assert_eq!(
+            fields,
+            [
+                HeaderField::Runs,
+                HeaderField::HardMode,
+                HeaderField::Location
+            ]
+        );
+        assert_eq!(inconsistencies[0].state, HeaderValue::Number(9));
+        assert_eq!(
+            inconsistencies[0].to_string(),
+            "header `runs` is 100, but the lua state has 9"
+        );
+
+        assert_eq!(
+            sync_header_from_state(&mut savefile, &lua_state),
+            inconsistencies
+        );
+        assert_eq!(check_consistency(&savefile, &lua_state), []);
+        assert!(savefile.hard_mode);
+        savefile.hard_mode = false;
+        assert_eq!(savefile, original);
+    }
+}
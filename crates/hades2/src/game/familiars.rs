@@ -0,0 +1,242 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, Set, TableMut, check_table, insert, remove, string, table, table_mut};
+use crate::saves::LuaValue;
+
+/// Ranks of each familiar upgrade, named like `FrogDamage`, `FrogDamage2` and `FrogDamage3`
+pub const MAX_FAMILIAR_UPGRADE_RANK: u32 = 3;
+
+/// Entry of the [`FAMILIARS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FamiliarInfo {
+    /// Key in `GameState.FamiliarStatus`, like `FrogFamiliar`
+    pub name: &'static str,
+    /// Name of the familiar, like `Frinos`
+    pub display_name: &'static str,
+    /// Prefix of the familiar's traits and upgrades, like `Frog`
+    pub prefix: &'static str,
+    /// Upgrades in `GameState.FamiliarUpgrades` at rank 1, where known
+    pub upgrades: &'static [&'static str],
+}
+
+impl FamiliarInfo {
+    pub fn max_level(&self) -> u32 {
+        self.upgrades.len() as u32 * MAX_FAMILIAR_UPGRADE_RANK
+    }
+}
+
+const fn familiar(
+    name: &'static str,
+    display_name: &'static str,
+    prefix: &'static str,
+    upgrades: &'static [&'static str],
+) -> FamiliarInfo {
+    FamiliarInfo {
+        name,
+        display_name,
+        prefix,
+        upgrades,
+    }
+}
+
+/// The familiars, in the order they are met.
+///
+/// Only upgrades seen in saves are listed, so the later familiars don't have any yet.
+#[rustfmt::skip]
+pub const FAMILIARS: &[FamiliarInfo] = &[
+    familiar("FrogFamiliar", "Frinos", "Frog", &["FrogDamage", "FrogHealthBonus", "FrogUses"]),
+    familiar("CatFamiliar", "Toula", "Cat", &["CatAttack", "CatLastStandHeal", "CatUses"]),
+    familiar("RavenFamiliar", "Raki", "Raven", &[]),
+    familiar("HoundFamiliar", "Hecuba", "Hound", &[]),
+    familiar("PolecatFamiliar", "Gale", "Polecat", &[]),
+];
+
+/// Looks up a familiar in the [`FAMILIARS`] catalogue by its key in `GameState.FamiliarStatus`
+pub fn familiar_info(name: &str) -> Option<&'static FamiliarInfo> {
+    FAMILIARS.iter().find(|info| info.name == name)
+}
+
+/// The name of rank `rank` of an upgrade, like `FrogDamage2`
+fn upgrade_name(upgrade: &str, rank: u32) -> String {
+    match rank {
+        1 => upgrade.to_owned(),
+        _ => format!("{upgrade}{rank}"),
+    }
+}
+
+/// State of a single familiar of the [`FAMILIARS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FamiliarState {
+    pub info: &'static FamiliarInfo,
+    pub unlocked: bool,
+    /// Total ranks of its upgrades
+    pub level: u32,
+    pub equipped: bool,
+}
+
+/// View over the familiars in `GameState`.
+///
+/// `FamiliarStatus` tracks which are unlocked, `FamiliarUpgrades` the upgrades bought for them
+/// and `EquippedFamiliar` the one equipped in the hub.
+#[derive(Debug, Clone, Copy)]
+pub struct Familiars<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Familiars<V> {
+    pub fn is_unlocked(&self, name: &str) -> bool {
+        table(&self.value, "FamiliarStatus")
+            .get_path(&format!("{name}.Unlocked"))
+            .and_then(LuaValue::as_bool)
+            == Some(true)
+    }
+    /// Rank of an upgrade like `FrogDamage`, where 0 means it wasn't bought
+    pub fn upgrade_rank(&self, upgrade: &str) -> u32 {
+        let upgrades = Set {
+            value: table(&self.value, "FamiliarUpgrades"),
+        };
+        (1..=MAX_FAMILIAR_UPGRADE_RANK)
+            .take_while(|&rank| upgrades.contains(&upgrade_name(upgrade, rank)))
+            .count() as u32
+    }
+    /// Total ranks of the familiar's upgrades, or 0 for familiars not in [`FAMILIARS`]
+    pub fn level(&self, name: &str) -> u32 {
+        familiar_info(name).map_or(0, |info| {
+            info.upgrades
+                .iter()
+                .map(|upgrade| self.upgrade_rank(upgrade))
+                .sum()
+        })
+    }
+    pub fn equipped<'v>(&'v self) -> Option<&'v str>
+    where
+        'a: 'v,
+    {
+        self.value.get("EquippedFamiliar")?.as_str()
+    }
+    /// Every familiar of the [`FAMILIARS`] catalogue, in its order
+    pub fn iter(&self) -> impl Iterator<Item = FamiliarState> + '_ {
+        FAMILIARS.iter().map(|info| FamiliarState {
+            info,
+            unlocked: self.is_unlocked(info.name),
+            level: self.level(info.name),
+            equipped: self.equipped() == Some(info.name),
+        })
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Familiars<V> {
+    /// Unlocks or locks a familiar, unequipping it when it is locked
+    pub fn set_unlocked(&mut self, name: &str, unlocked: bool) -> Result<()> {
+        familiar_info(name).ok_or_else(|| Error::UnknownFamiliar(name.to_owned()))?;
+        check_table(&self.value, "FamiliarStatus")?;
+        check_table(table(&self.value, "FamiliarStatus"), name)?;
+        if !unlocked && self.equipped() == Some(name) {
+            self.unequip();
+        }
+        let status = table_mut(table_mut(&mut self.value, "FamiliarStatus")?, name)?;
+        insert(status, "Unlocked", LuaValue::Bool(unlocked));
+        status
+            .as_table_mut()
+            .unwrap()
+            .get_or_insert("RestTicks", LuaValue::Number(0.0));
+        Ok(())
+    }
+    /// Sets the total ranks of the familiar's upgrades, spread evenly in catalogue order
+    pub fn set_level(&mut self, name: &str, level: u32) -> Result<()> {
+        let info = familiar_info(name).ok_or_else(|| Error::UnknownFamiliar(name.to_owned()))?;
+        if level > info.max_level() {
+            return Err(Error::InvalidFamiliarLevel {
+                name: name.to_owned(),
+                level,
+                max_level: info.max_level(),
+            });
+        }
+        let tracks = info.upgrades.len() as u32;
+        let mut upgrades = Set {
+            value: TableMut::new(&mut self.value, &["FamiliarUpgrades"])?,
+        };
+        for (i, upgrade) in (0..).zip(info.upgrades) {
+            let rank = level / tracks + u32::from(i < level % tracks);
+            for r in 1..=MAX_FAMILIAR_UPGRADE_RANK {
+                upgrades.set(&upgrade_name(upgrade, r), r <= rank);
+            }
+        }
+        Ok(())
+    }
+    pub fn equip(&mut self, name: &str) -> Result<()> {
+        familiar_info(name).ok_or_else(|| Error::UnknownFamiliar(name.to_owned()))?;
+        if !self.is_unlocked(name) {
+            return Err(Error::FamiliarLocked(name.to_owned()));
+        }
+        insert(&mut self.value, "EquippedFamiliar", string(name));
+        Ok(())
+    }
+    pub fn unequip(&mut self) {
+        remove(&mut self.value, "EquippedFamiliar");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::game::{Error, State};
+    use crate::saves::Savefile;
+
+    #[test]
+    fn familiars() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let state = State::new(&lua_state);
+        let familiars = state.familiars();
+        let unlocked: Vec<_> = familiars
+            .iter()
+            .filter(|familiar| familiar.unlocked)
+            .map(|familiar| (familiar.info.name, familiar.level, familiar.equipped))
+            .collect();
+        assert_eq!(
+            unlocked,
+            [("FrogFamiliar", 9, true), ("CatFamiliar", 2, false)]
+        );
+        assert_eq!(familiars.upgrade_rank("CatLastStandHeal"), 2);
+
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        assert!(state.familiars().iter().all(|familiar| !familiar.unlocked));
+        assert_eq!(state.familiars().equipped(), None);
+    }
+
+    #[test]
+    fn set_familiars() {
+        let (_, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut familiars = state.familiars_mut().unwrap();
+
+        assert!(matches!(
+            familiars.equip("CatFamiliar"),
+            Err(Error::FamiliarLocked(_))
+        ));
+        familiars.set_unlocked("CatFamiliar", true).unwrap();
+        familiars.equip("CatFamiliar").unwrap();
+        assert_eq!(familiars.equipped(), Some("CatFamiliar"));
+
+        familiars.set_level("CatFamiliar", 4).unwrap();
+        assert_eq!(familiars.level("CatFamiliar"), 4);
+        assert_eq!(familiars.upgrade_rank("CatAttack"), 2);
+        assert_eq!(familiars.upgrade_rank("CatUses"), 1);
+        familiars.set_level("CatFamiliar", 1).unwrap();
+        assert_eq!(familiars.upgrade_rank("CatAttack"), 1);
+        assert_eq!(familiars.upgrade_rank("CatLastStandHeal"), 0);
+        assert!(matches!(
+            familiars.set_level("CatFamiliar", 10),
+            Err(Error::InvalidFamiliarLevel { max_level: 9, .. })
+        ));
+
+        familiars.set_unlocked("CatFamiliar", false).unwrap();
+        assert!(!familiars.is_unlocked("CatFamiliar"));
+        assert_eq!(familiars.equipped(), None);
+        assert!(matches!(
+            familiars.set_unlocked("Unknown", true),
+            Err(Error::UnknownFamiliar(_))
+        ));
+    }
+}
@@ -0,0 +1,338 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Resources, Result, Set, TableMut, check_table, table};
+use crate::saves::LuaValue;
+
+/// Entry of the [`INCANTATIONS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncantationInfo {
+    /// Key in `GameState.WorldUpgrades`
+    pub name: &'static str,
+    /// Title at the cauldron, like `Fated List of Minor Prophecies`
+    pub display_name: &'static str,
+    /// Ingredients consumed by the cauldron, as keys of `GameState.Resources`
+    pub cost: &'static [(&'static str, u32)],
+}
+
+const fn incantation(
+    name: &'static str,
+    display_name: &'static str,
+    cost: &'static [(&'static str, u32)],
+) -> IncantationInfo {
+    IncantationInfo {
+        name,
+        display_name,
+        cost,
+    }
+}
+
+/// The incantations of the crossroads cauldron, roughly in the order they are revealed.
+///
+/// Ingredient costs are those of the game version the test saves come from. They change between
+/// game versions and are not stored in savefiles, so [`Incantations::uncast`] takes the refund as an argument.
+#[rustfmt::skip]
+pub const INCANTATIONS: &[IncantationInfo] = &[
+    incantation("WorldUpgradeQuestLog", "Fated List of Minor Prophecies", &[("PlantFMoly", 1)]),
+    incantation("WorldUpgradeCardUpgradeSystem", "Unraveling a Fateful Bond", &[("PlantFMoly", 1), ("MetaCardPointsCommon", 10)]),
+    incantation("WorldUpgradeRelationshipBar", "Gifts of Companionship", &[("PlantFMoly", 1), ("GiftPoints", 1)]),
+    incantation("WorldUpgradeFamiliarSystem", "Summoning of Familiar Spirits", &[("PlantFNightshade", 1), ("FamiliarPoints", 1)]),
+    incantation("WorldUpgradeGarden", "Fertile Soil of the Crossroads", &[("PlantFMoly", 2), ("OreFSilver", 2)]),
+    incantation("WorldUpgradeGardenT2", "Garden of Ripening Plenty", &[("PlantGLotus", 2), ("OreGLime", 2)]),
+    incantation("WorldUpgradeGardenT3", "Garden of Abundant Harvest", &[("PlantHMyrtle", 2), ("OreHGlassrock", 2)]),
+    incantation("WorldUpgradeHarvestUpgrade", "Gathering of Greater Bounties", &[("PlantIShaderot", 2), ("OreIMarble", 2)]),
+    incantation("WorldUpgradeMarket", "Unlocking the Broker", &[("OreFSilver", 3)]),
+    incantation("WorldUpgradeSellShop", "Trading with the Wretched Broker", &[("OreFSilver", 2), ("PlantFMoly", 1)]),
+    incantation("WorldUpgradeGiftsShop", "Offerings of Nectar", &[("PlantGCattail", 2)]),
+    incantation("WorldUpgradeNectar", "Distillation of Nectar", &[("PlantGLotus", 2), ("MetaCurrency", 50)]),
+    incantation("WorldUpgradeToolsShop", "Tools of the Trade", &[("OreFSilver", 2)]),
+    incantation("WorldUpgradeToolUpgradeSystem", "Honing of Gathering Tools", &[("OreHGlassrock", 3), ("MixerFBoss", 1)]),
+    incantation("WorldUpgradeWeaponUpgradeSystem", "Awakening of Nocturnal Arms", &[("MixerGBoss", 1), ("WeaponPointsRare", 1)]),
+    incantation("WorldUpgradeWellShops", "Wells of Charon", &[("OreFSilver", 2), ("MetaCurrency", 20)]),
+    incantation("WorldUpgradePostBossWellShops", "Wells after the Guardians", &[("MixerFBoss", 1), ("OreGLime", 2)]),
+    incantation("WorldUpgradePostBossGiftRack", "Tributes after the Guardians", &[("MixerGBoss", 1), ("PlantGCattail", 2)]),
+    incantation("WorldUpgradeSurfaceShops", "Shops of the Surface", &[("OreNBronze", 2), ("PlantNGarlic", 1)]),
+    incantation("WorldUpgradePostBossSurfaceShops", "Shops beyond the Cyclops", &[("OreOIron", 2), ("PlantODriftwood", 2)]),
+    incantation("WorldUpgradeBathHouse", "Restoration of the Bath House", &[("OreGLime", 3), ("PlantGLotus", 1)]),
+    incantation("WorldUpgradeTaverna", "Reopening of the Taverna", &[("OreHGlassrock", 2), ("PlantHWheat", 2)]),
+    incantation("WorldUpgradeFountainUpgrade1", "Purification of the Fountains", &[("PlantFMoly", 2), ("MixerFBoss", 1)]),
+    incantation("WorldUpgradeFountainUpgrade2", "Enhancement of the Fountains", &[("PlantHMyrtle", 2), ("MixerHBoss", 1)]),
+    incantation("WorldUpgradeErebusReprieve", "Reprieve of Erebus", &[("MixerFBoss", 1), ("PlantFNightshade", 2)]),
+    incantation("WorldUpgradeOceanusReprieve", "Reprieve of Oceanus", &[("MixerGBoss", 1), ("PlantGLotus", 2)]),
+    incantation("WorldUpgradeTartarusReprieve", "Reprieve of Tartarus", &[("MixerIBoss", 1), ("PlantIPoppy", 2)]),
+    incantation("WorldUpgradeThessalyReprieve", "Reprieve of Thessaly", &[("OreOIron", 2), ("PlantOMandrake", 2)]),
+    incantation("WorldUpgradeErebusSafeZones", "Safe Passage through Erebus", &[("PlantFNightshade", 2), ("OreFSilver", 2)]),
+    incantation("WorldUpgradeSurfacePenaltyCure", "Permeation of Witching-Wards", &[("MixerFBoss", 1), ("PlantNGarlic", 3)]),
+    incantation("WorldUpgradeResourceFinder", "Locating of Gathering Spots", &[("OreFSilver", 1), ("PlantFMoly", 1)]),
+    incantation("WorldUpgradeFieldsRewardFinder", "Foresight of the Mourning Fields", &[("PlantHMyrtle", 2)]),
+    incantation("WorldUpgradeFishingPoint", "Discovery of Fishing Spots", &[("FishFCommon", 1)]),
+    incantation("WorldUpgradeElementalBoons", "Affinity of the Elements", &[("MixerFBoss", 1), ("MemPointsCommon", 20)]),
+    incantation("WorldUpgradeUnusedWeaponBonus", "Favor of Unused Arms", &[("OreGLime", 2), ("PlantGCattail", 1)]),
+    incantation("WorldUpgradeBreakableValue1", "Treasures of the Urns", &[("OreFSilver", 2)]),
+    incantation("WorldUpgradeMorosUnlock", "Summoning of Doom", &[("PlantHMyrtle", 1), ("MixerHBoss", 1)]),
+    incantation("WorldUpgradeShadeMercs", "Conjuring of Shady Allies", &[("PlantIShaderot", 2), ("MixerIBoss", 1)]),
+    incantation("WorldUpgradeAltRunDoor", "Path of Stars to the Surface", &[("MixerFBoss", 1), ("OreFSilver", 3)]),
+    incantation("WorldUpgradeMixerShadow", "Transmutation of Shadow", &[("MixerHBoss", 1), ("PlantIShaderot", 1)]),
+    incantation("WorldUpgradeBountyBoard", "Unsealing of the Pitch-Black Stone", &[("MixerIBoss", 1), ("OreIMarble", 2)]),
+    incantation("WorldUpgradeChallengeSwitches1", "Opening of Infernal Troves", &[("OreGLime", 2), ("MixerGBoss", 1)]),
+    incantation("WorldUpgradeCardUpgradePoints", "Grasp of the Moon", &[("PlantIPoppy", 1), ("MixerFBoss", 1)]),
+    incantation("WorldUpgradeCardUpgradePoints2", "Greater Grasp of the Moon", &[("PlantOMandrake", 1), ("MixerIBoss", 1)]),
+    incantation("WorldUpgradeFamiliarPoints", "Delights for Familiars", &[("PlantNMoss", 2)]),
+    incantation("WorldUpgradeFamiliarRest", "Resting Place for Familiars", &[("PlantODriftwood", 2)]),
+    incantation("WorldUpgradePinning", "Marking of Desired Goods", &[("OreFSilver", 1)]),
+    incantation("WorldUpgradeBoonList", "Record of Olympian Favor", &[("PlantGLotus", 1)]),
+    incantation("WorldUpgradeRunHistory", "Chronicle of Past Nights", &[("PlantFMoly", 1)]),
+    incantation("WorldUpgradeGameStats", "Tally of Deeds", &[("PlantFMoly", 1)]),
+    incantation("WorldUpgradeEphyraZoomOut", "Overlook of Ephyra", &[("PlantNGarlic", 1)]),
+    incantation("WorldUpgradeMusicPlayer", "Songs of the Crossroads", &[("OreNBronze", 1)]),
+    incantation("WorldUpgradeTimeStop", "Stillness of Time", &[("MixerIBoss", 2), ("PlantChaosThalamus", 1)]),
+    incantation("WorldUpgradePauseChronosFight", "Halting of the Titan", &[("MixerIBoss", 1), ("OreIMarble", 2)]),
+];
+
+/// Looks up an incantation in the [`INCANTATIONS`] catalogue by its key in `GameState.WorldUpgrades`
+pub fn incantation_info(name: &str) -> Option<&'static IncantationInfo> {
+    INCANTATIONS.iter().find(|info| info.name == name)
+}
+
+/// State of a single incantation of the [`INCANTATIONS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncantationState {
+    pub info: &'static IncantationInfo,
+    /// Shown at the cauldron, recorded in `WorldUpgradesRevealed`
+    pub unlocked: bool,
+    /// Recorded in both `WorldUpgrades` and `WorldUpgradesAdded`
+    pub cast: bool,
+}
+
+impl IncantationState {
+    /// Ingredients from the [`INCANTATIONS`] catalogue
+    pub fn cost(&self) -> &'static [(&'static str, u32)] {
+        self.info.cost
+    }
+}
+
+/// View over the incantations cast at the crossroads cauldron.
+///
+/// The game shares its tables with other world upgrades, like cosmetics and weapons,
+/// see [`Unlocks::world_upgrades`](super::Unlocks::world_upgrades).
+#[derive(Debug, Clone, Copy)]
+pub struct Incantations<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Incantations<V> {
+    fn set(&self, key: &str) -> Set<&LuaValue<'a>> {
+        Set {
+            value: table(&self.value, key),
+        }
+    }
+
+    pub fn is_unlocked(&self, name: &str) -> bool {
+        self.set("WorldUpgradesRevealed").contains(name)
+    }
+    pub fn is_cast(&self, name: &str) -> bool {
+        self.set("WorldUpgrades").contains(name)
+    }
+    /// Every incantation of the [`INCANTATIONS`] catalogue, in its order
+    pub fn iter(&self) -> impl Iterator<Item = IncantationState> + '_ {
+        INCANTATIONS.iter().map(|info| IncantationState {
+            info,
+            unlocked: self.is_unlocked(info.name),
+            cast: self.is_cast(info.name),
+        })
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Incantations<V> {
+    fn set_mut(&mut self, key: &'static str) -> Result<Set<TableMut<'_, V>>> {
+        Ok(Set {
+            value: TableMut::new(&mut self.value, &[key])?,
+        })
+    }
+
+    /// Marks an incantation as cast and revealed, without consuming its ingredients
+    pub fn cast(&mut self, name: &str) -> Result<()> {
+        incantation_info(name).ok_or_else(|| Error::UnknownIncantation(name.to_owned()))?;
+        let keys = [
+            "WorldUpgrades",
+            "WorldUpgradesAdded",
+            "WorldUpgradesRevealed",
+            "WorldUpgradesViewed",
+        ];
+        for key in keys {
+            check_table(&self.value, key)?;
+        }
+        for key in keys {
+            self.set_mut(key)?.insert(name);
+        }
+        Ok(())
+    }
+
+    /// Reverts a cast incantation and adds `refund` back to `GameState.Resources`,
+    /// usually the [`IncantationInfo::cost`] of the catalogue.
+    ///
+    /// Returns `false` without refunding anything if the incantation wasn't cast.
+    /// It stays revealed, so it can be cast again in the game.
+    pub fn uncast(&mut self, name: &str, refund: &[(&str, u32)]) -> Result<bool> {
+        incantation_info(name).ok_or_else(|| Error::UnknownIncantation(name.to_owned()))?;
+        if !self.is_cast(name) {
+            return Ok(false);
+        }
+
+        check_table(&self.value, "WorldUpgradesAdded")?;
+        let mut resources = Resources {
+            value: TableMut::new(&mut self.value, &["Resources"])?,
+        };
+        // check every ingredient first, so that an error doesn't leave a partial refund
+        for &(resource, _) in refund {
+            resources.amount(resource)?;
+        }
+        for &(resource, amount) in refund {
+            resources.add(resource, amount.into())?;
+        }
+
+        self.set_mut("WorldUpgrades")?.remove(name);
+        self.set_mut("WorldUpgradesAdded")?.remove(name);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{INCANTATIONS, incantation_info};
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::game::{Error, State};
+    use crate::saves::{Savefile, read_luatext};
+
+    #[test]
+    fn catalogue() {
+        for info in INCANTATIONS {
+            assert!(
+                info.cost
+                    .iter()
+                    .all(|(resource, _)| crate::game::resource_info(resource).is_some())
+            );
+        }
+
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        let incantations = state.incantations();
+        assert!(incantations.is_cast("WorldUpgradeQuestLog"));
+        assert!(incantations.is_unlocked("WorldUpgradeTaverna"));
+        assert!(!incantations.is_cast("WorldUpgradeTaverna"));
+        assert!(
+            incantations
+                .iter()
+                .all(|state| state.unlocked || !state.cast)
+        );
+    }
+
+    #[test]
+    fn cast_uncast() {
+        let mut lua_state = read_luatext(
+            r#"{ GameState = {
+                Resources = { OreHGlassrock = 1 },
+                WorldUpgrades = { WorldUpgradeMarket = true },
+                WorldUpgradesAdded = { WorldUpgradeMarket = true },
+            } }"#,
+        )
+        .unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut incantations = state.incantations_mut().unwrap();
+
+        let cost = |name| incantation_info(name).unwrap().cost;
+        incantations.cast("WorldUpgradeTaverna").unwrap();
+        assert!(incantations.is_cast("WorldUpgradeTaverna"));
+        let taverna = incantations
+            .iter()
+            .find(|state| state.info.name == "WorldUpgradeTaverna")
+            .unwrap();
+        assert_eq!(taverna.cost(), [("OreHGlassrock", 2), ("PlantHWheat", 2)]);
+        assert!(
+            incantations
+                .uncast("WorldUpgradeTaverna", taverna.cost())
+                .unwrap()
+        );
+        assert!(
+            !incantations
+                .uncast("WorldUpgradeTaverna", taverna.cost())
+                .unwrap()
+        );
+        assert!(incantations.is_unlocked("WorldUpgradeTaverna"));
+        assert!(
+            incantations
+                .uncast("WorldUpgradeMarket", cost("WorldUpgradeMarket"))
+                .unwrap()
+        );
+        assert!(matches!(
+            incantations.cast("WorldUpgradeUnknown"),
+            Err(Error::UnknownIncantation(_))
+        ));
+
+        let expected = read_luatext(
+            r#"{ GameState = {
+                Resources = { OreFSilver = 3, OreHGlassrock = 3, PlantHWheat = 2 },
+                WorldUpgrades = {},
+                WorldUpgradesAdded = {},
+                WorldUpgradesRevealed = { WorldUpgradeTaverna = true },
+                WorldUpgradesViewed = { WorldUpgradeTaverna = true },
+            } }"#,
+        )
+        .unwrap();
+        assert!(lua_state.canonical_eq(&expected));
+    }
+
+    #[test]
+    fn uncast_refund_is_atomic() {
+        let mut lua_state = read_luatext(
+            r#"{ GameState = {
+                Resources = { OreHGlassrock = 1, PlantHWheat = -1 },
+                WorldUpgrades = { WorldUpgradeTaverna = true },
+                WorldUpgradesAdded = { WorldUpgradeTaverna = true },
+            } }"#,
+        )
+        .unwrap();
+        let expected = lua_state.clone();
+        let mut state = State::new(&mut lua_state);
+        let mut incantations = state.incantations_mut().unwrap();
+
+        assert!(matches!(
+            incantations.uncast(
+                "WorldUpgradeTaverna",
+                &[("OreHGlassrock", 2), ("PlantHWheat", 2)]
+            ),
+            Err(Error::InvalidAmount { .. })
+        ));
+        assert!(incantations.is_cast("WorldUpgradeTaverna"));
+        assert!(lua_state.canonical_eq(&expected));
+
+        let mut state = State::new(&mut lua_state);
+        let mut incantations = state.incantations_mut().unwrap();
+        assert!(
+            incantations
+                .uncast("WorldUpgradeTaverna", &[("OreHGlassrock", 5)])
+                .unwrap()
+        );
+        assert_eq!(state.resources().get("OreHGlassrock"), Some(6));
+        assert_eq!(state.resources().get("PlantHWheat"), None);
+    }
+
+    #[test]
+    fn uncast_cast_round_trip() {
+        let (_, original) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let mut lua_state = original.clone();
+        let mut state = State::new(&mut lua_state);
+        let mut incantations = state.incantations_mut().unwrap();
+
+        let mut cast = 0;
+        for info in INCANTATIONS {
+            if incantations.is_cast(info.name) {
+                cast += 1;
+                assert!(incantations.uncast(info.name, &[]).unwrap());
+                assert!(!incantations.is_cast(info.name));
+                incantations.cast(info.name).unwrap();
+            }
+        }
+        assert!(cast > 0);
+        assert!(lua_state.canonical_eq(&original));
+    }
+}
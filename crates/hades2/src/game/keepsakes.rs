@@ -0,0 +1,234 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, Set, TableMut, check_table, insert, remove, string, table, table_mut};
+use crate::saves::LuaValue;
+
+/// Encounters a keepsake needs to be carried through to reach each rank, starting at rank 1
+pub const KEEPSAKE_RANK_CHAMBERS: [u32; 3] = [0, 25, 75];
+pub const MAX_KEEPSAKE_RANK: u32 = KEEPSAKE_RANK_CHAMBERS.len() as u32;
+
+/// Entry of the [`KEEPSAKES`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepsakeInfo {
+    /// Key in `GameState.GiftPresentation` and `GameState.KeepsakeChambers`
+    pub name: &'static str,
+    /// English name, like `Silver Wheel`
+    pub display_name: &'static str,
+}
+
+const fn keepsake(name: &'static str, display_name: &'static str) -> KeepsakeInfo {
+    KeepsakeInfo { name, display_name }
+}
+
+/// The keepsakes, which the game awards for the first gift to a character
+#[rustfmt::skip]
+pub const KEEPSAKES: &[KeepsakeInfo] = &[
+    keepsake("ManaOverTimeRefundKeepsake", "Silver Wheel"),
+    keepsake("BossPreDamageKeepsake", "Knuckle Bones"),
+    keepsake("ReincarnationKeepsake", "Luckier Tooth"),
+    keepsake("DoorHealReserveKeepsake", "Ghost Onion"),
+    keepsake("DeathVengeanceKeepsake", "Evil Eye"),
+    keepsake("BlockDeathKeepsake", "Engraved Pin"),
+    keepsake("BonusMoneyKeepsake", "Gold Purse"),
+    keepsake("LowHealthCritKeepsake", "White Antler"),
+    keepsake("TimedBuffKeepsake", "Moon Beam"),
+    keepsake("ArmorGainKeepsake", "Silken Sash"),
+    keepsake("FountainRarityKeepsake", "Crystal Figurine"),
+    keepsake("UnpickedBoonKeepsake", "Concave Stone"),
+    keepsake("EscalatingKeepsake", "Lion Fang"),
+    keepsake("DecayingBoostKeepsake", "Aromatic Phial"),
+    keepsake("DamagedDamageBoostKeepsake", "Discordant Bell"),
+    keepsake("TempHammerKeepsake", "Feather Frame"),
+    keepsake("RandomBlessingKeepsake", "Transcendent Embryo"),
+    keepsake("SpellTalentKeepsake", "Witch's Staff"),
+    keepsake("BossMetaUpgradeKeepsake", "Time Piece"),
+    keepsake("ForceZeusBoonKeepsake", "Cloud Bangle"),
+    keepsake("ForceHeraBoonKeepsake", "Iridescent Fan"),
+    keepsake("ForcePoseidonBoonKeepsake", "Vivid Sea"),
+    keepsake("ForceDemeterBoonKeepsake", "Barley Sheaf"),
+    keepsake("ForceApolloBoonKeepsake", "Purest Hope"),
+    keepsake("ForceAphroditeBoonKeepsake", "Beautiful Mirror"),
+    keepsake("ForceHephaestusBoonKeepsake", "Adamant Shard"),
+    keepsake("ForceHestiaBoonKeepsake", "Everlasting Ember"),
+];
+
+/// Looks up a keepsake in the [`KEEPSAKES`] catalogue by its key in `GameState.GiftPresentation`
+pub fn keepsake_info(name: &str) -> Option<&'static KeepsakeInfo> {
+    KEEPSAKES.iter().find(|info| info.name == name)
+}
+
+/// Rank of a keepsake carried through `chambers` encounters
+pub fn keepsake_rank(chambers: u32) -> u32 {
+    KEEPSAKE_RANK_CHAMBERS
+        .iter()
+        .filter(|&&threshold| chambers >= threshold)
+        .count() as u32
+}
+
+/// State of a single keepsake of the [`KEEPSAKES`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepsakeState {
+    pub info: &'static KeepsakeInfo,
+    pub owned: bool,
+    /// Encounters the keepsake was carried through, from `KeepsakeChambers`
+    pub chambers: u32,
+    pub equipped: bool,
+}
+
+impl KeepsakeState {
+    pub fn rank(&self) -> u32 {
+        keepsake_rank(self.chambers)
+    }
+}
+
+/// View over the keepsakes in `GameState`.
+///
+/// Owned keepsakes are in `GiftPresentation`, and `LastAwardTrait` is the one equipped in the hub.
+#[derive(Debug, Clone, Copy)]
+pub struct Keepsakes<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Keepsakes<V> {
+    pub fn is_owned(&self, name: &str) -> bool {
+        let owned = Set {
+            value: table(&self.value, "GiftPresentation"),
+        };
+        owned.contains(name)
+    }
+    pub fn chambers(&self, name: &str) -> u32 {
+        table(&self.value, "KeepsakeChambers")
+            .get(name)
+            .and_then(LuaValue::as_u32)
+            .unwrap_or(0)
+    }
+    pub fn rank(&self, name: &str) -> u32 {
+        keepsake_rank(self.chambers(name))
+    }
+    pub fn equipped<'v>(&'v self) -> Option<&'v str>
+    where
+        'a: 'v,
+    {
+        self.value.get("LastAwardTrait")?.as_str()
+    }
+    /// Every keepsake of the [`KEEPSAKES`] catalogue, in its order
+    pub fn iter(&self) -> impl Iterator<Item = KeepsakeState> + '_ {
+        KEEPSAKES.iter().map(|info| KeepsakeState {
+            info,
+            owned: self.is_owned(info.name),
+            chambers: self.chambers(info.name),
+            equipped: self.equipped() == Some(info.name),
+        })
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Keepsakes<V> {
+    /// Adds or removes a keepsake, unequipping it when it is removed
+    pub fn set_owned(&mut self, name: &str, owned: bool) -> Result<()> {
+        keepsake_info(name).ok_or_else(|| Error::UnknownKeepsake(name.to_owned()))?;
+        check_table(&self.value, "GiftPresentation")?;
+        if !owned && self.equipped() == Some(name) {
+            self.unequip();
+        }
+        let mut set = Set {
+            value: TableMut::new(&mut self.value, &["GiftPresentation"])?,
+        };
+        set.set(name, owned);
+        Ok(())
+    }
+    /// Sets the rank of an owned keepsake through the encounters it was carried through.
+    ///
+    /// Encounters which already reach `rank`, but not the next one, are kept.
+    pub fn set_rank(&mut self, name: &str, rank: u32) -> Result<()> {
+        keepsake_info(name).ok_or_else(|| Error::UnknownKeepsake(name.to_owned()))?;
+        if !self.is_owned(name) {
+            return Err(Error::KeepsakeNotOwned(name.to_owned()));
+        }
+        if !(1..=MAX_KEEPSAKE_RANK).contains(&rank) {
+            return Err(Error::InvalidKeepsakeRank {
+                name: name.to_owned(),
+                rank,
+            });
+        }
+        if self.rank(name) != rank {
+            let chambers = KEEPSAKE_RANK_CHAMBERS[rank as usize - 1];
+            let keepsakes = table_mut(&mut self.value, "KeepsakeChambers")?;
+            insert(keepsakes, name, LuaValue::Number(chambers.into()));
+        }
+        Ok(())
+    }
+    pub fn equip(&mut self, name: &str) -> Result<()> {
+        keepsake_info(name).ok_or_else(|| Error::UnknownKeepsake(name.to_owned()))?;
+        if !self.is_owned(name) {
+            return Err(Error::KeepsakeNotOwned(name.to_owned()));
+        }
+        insert(&mut self.value, "LastAwardTrait", string(name));
+        Ok(())
+    }
+    pub fn unequip(&mut self) {
+        remove(&mut self.value, "LastAwardTrait");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KEEPSAKES, keepsake_rank};
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::game::{Error, State};
+    use crate::saves::Savefile;
+
+    #[test]
+    fn keepsakes() {
+        for data in [TEST_PROFILE_V17, TEST_PROFILE_V18] {
+            let (_, lua_state) = Savefile::parse(data).unwrap();
+            let state = State::new(&lua_state);
+            let keepsakes = state.keepsakes();
+            let equipped = keepsakes.equipped().unwrap();
+            assert!(KEEPSAKES.iter().any(|info| info.name == equipped));
+            assert!(keepsakes.is_owned(equipped));
+        }
+
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let state = State::new(&lua_state);
+        let keepsakes = state.keepsakes();
+        assert_eq!(keepsakes.equipped(), Some("DoorHealReserveKeepsake"));
+        assert_eq!(
+            keepsakes.iter().filter(|keepsake| keepsake.owned).count(),
+            27
+        );
+        assert_eq!(keepsakes.chambers("ReincarnationKeepsake"), 233);
+        assert_eq!(keepsakes.rank("ReincarnationKeepsake"), 3);
+        assert_eq!(keepsakes.rank("LowHealthCritKeepsake"), 1);
+        assert_eq!(keepsake_rank(25), 2);
+    }
+
+    #[test]
+    fn set_keepsakes() {
+        let (_, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut keepsakes = state.keepsakes_mut().unwrap();
+
+        assert!(matches!(
+            keepsakes.equip("ForceHeraBoonKeepsake"),
+            Err(Error::KeepsakeNotOwned(_))
+        ));
+        keepsakes.set_owned("ForceHeraBoonKeepsake", true).unwrap();
+        keepsakes.equip("ForceHeraBoonKeepsake").unwrap();
+        keepsakes.set_rank("ForceHeraBoonKeepsake", 3).unwrap();
+        assert_eq!(keepsakes.chambers("ForceHeraBoonKeepsake"), 75);
+        keepsakes.set_rank("ForceHeraBoonKeepsake", 3).unwrap();
+        assert_eq!(keepsakes.chambers("ForceHeraBoonKeepsake"), 75);
+        assert!(matches!(
+            keepsakes.set_rank("ForceHeraBoonKeepsake", 4),
+            Err(Error::InvalidKeepsakeRank { rank: 4, .. })
+        ));
+
+        keepsakes.set_owned("ForceHeraBoonKeepsake", false).unwrap();
+        assert!(!keepsakes.is_owned("ForceHeraBoonKeepsake"));
+        assert_eq!(keepsakes.equipped(), None);
+        assert!(matches!(
+            keepsakes.set_owned("Unknown", true),
+            Err(Error::UnknownKeepsake(_))
+        ));
+    }
+}
@@ -0,0 +1,601 @@
+//! Typed views over the well-known parts of the lua state.
+//!
+//! A view borrows the [`LuaValue`] it was created from, either shared (`&LuaValue`) for reading
+//! or exclusively (`&mut LuaValue`) for reading and writing.
+//! Only the entries a method is about are touched, so unknown fields stay as they are
+//! and an edited state serializes back losslessly.
+//!
+//! Missing tables read as empty. Mutable views only insert the tables they need once something
+//! is written to them, and setters check their arguments first, so a failed call leaves the
+//! lua state unchanged.
+//!
+//! ```no_run
+//! # fn main() -> hades2::Result<()> {
+//! # let data = Vec::new();
+//! use hades2::game::State;
+//! use hades2::saves::Savefile;
+//!
+//! let (_, mut lua_state) = Savefile::parse(&data)?;
+//! let mut state = State::new(&mut lua_state);
+//! let ash = state.resources().get("CardUpgradePoints").unwrap_or(0);
+//! state.resources_mut()?.set("CardUpgradePoints", ash + 100)?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod cards;
+mod consistency;
+mod familiars;
+mod incantations;
+mod keepsakes;
+mod quests;
+mod relationships;
+mod resources;
+mod runs;
+mod settings;
+#[cfg(test)]
+mod testdata;
+mod unlocks;
+mod vows;
+mod weapons;
+
+use std::ops::{Deref, DerefMut};
+
+use crate::saves::LuaValue;
+
+pub use cards::{CARDS, CardInfo, CardState, Cards, MAX_CARD_LEVEL, card_info};
+pub use consistency::{
+    HeaderField, HeaderValue, Inconsistency, check_consistency, sync_header_from_state,
+};
+pub use familiars::{
+    FAMILIARS, FamiliarInfo, FamiliarState, Familiars, MAX_FAMILIAR_UPGRADE_RANK, familiar_info,
+};
+pub use incantations::{
+    INCANTATIONS, IncantationInfo, IncantationState, Incantations, incantation_info,
+};
+pub use keepsakes::{
+    KEEPSAKE_RANK_CHAMBERS, KEEPSAKES, KeepsakeInfo, KeepsakeState, Keepsakes, MAX_KEEPSAKE_RANK,
+    keepsake_info, keepsake_rank,
+};
+pub use quests::{QUESTS, QuestInfo, QuestProgress, QuestState, QuestStatus, Quests, quest_info};
+pub use relationships::{
+    RELATIONSHIPS, Relationship, RelationshipInfo, Relationships, relationship_info,
+};
+pub use resources::{RESOURCES, ResourceCategory, ResourceInfo, Resources, resource_info};
+pub use runs::{Run, RunHistory, RunOutcome, RunRecord};
+pub use settings::Settings;
+pub use unlocks::Unlocks;
+pub use vows::{VOWS, VowInfo, VowState, Vows, vow_info};
+pub use weapons::{
+    AspectInfo, AspectState, MAX_ASPECT_RANK, WEAPONS, WeaponInfo, Weapons, aspect_info,
+    weapon_info,
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("expected `{0}` to be a table")]
+    NotATable(String),
+    #[error("resource `{name}` has the invalid amount {value}")]
+    InvalidAmount { name: String, value: String },
+    #[error("cannot add {delta} to resource `{name}` with amount {current}")]
+    AmountOutOfRange {
+        name: String,
+        current: u32,
+        delta: i64,
+    },
+    #[error("unknown arcana card `{0}`")]
+    UnknownCard(String),
+    #[error("arcana card `{0}` is locked")]
+    CardLocked(String),
+    #[error("arcana card `{name}` cannot be at level {level}")]
+    InvalidCardLevel { name: String, level: u32 },
+    #[error("grasp {grasp} is below the cost {equipped} of the equipped cards")]
+    GraspTooLow { grasp: u32, equipped: u32 },
+    #[error("unknown character `{0}`")]
+    UnknownCharacter(String),
+    #[error("relationship with `{name}` cannot be at level {level}, the maximum is {max_level}")]
+    InvalidHeartLevel {
+        name: String,
+        level: u32,
+        max_level: u32,
+    },
+    #[error("the gift text lines of `{0}` are unknown, so its hearts cannot be changed")]
+    UnknownGiftTextLines(String),
+    #[error("unknown incantation `{0}`")]
+    UnknownIncantation(String),
+    #[error("unknown weapon `{0}`")]
+    UnknownWeapon(String),
+    #[error("unknown aspect `{0}`")]
+    UnknownAspect(String),
+    #[error("weapon `{0}` is locked")]
+    WeaponLocked(String),
+    #[error("aspect `{name}` cannot be at rank {rank}")]
+    InvalidAspectRank { name: String, rank: u32 },
+    #[error("unknown keepsake `{0}`")]
+    UnknownKeepsake(String),
+    #[error("keepsake `{0}` is not owned")]
+    KeepsakeNotOwned(String),
+    #[error("keepsake `{name}` cannot be at rank {rank}")]
+    InvalidKeepsakeRank { name: String, rank: u32 },
+    #[error("unknown familiar `{0}`")]
+    UnknownFamiliar(String),
+    #[error("familiar `{0}` is locked")]
+    FamiliarLocked(String),
+    #[error("familiar `{name}` cannot be at level {level}, the maximum is {max_level}")]
+    InvalidFamiliarLevel {
+        name: String,
+        level: u32,
+        max_level: u32,
+    },
+    #[error("unknown quest `{0}`")]
+    UnknownQuest(String),
+    #[error("the reward of quest `{0}` was already claimed")]
+    QuestCashedOut(String),
+    #[error("unknown vow `{0}`")]
+    UnknownVow(String),
+    #[error("vow `{name}` cannot be at rank {rank}, the maximum is {max_rank}")]
+    InvalidVowRank {
+        name: String,
+        rank: u32,
+        max_rank: u32,
+    },
+    #[error("the header has {header} active shrine points, but the vows add up to {vows} fear")]
+    FearMismatch { header: u32, vows: u32 },
+}
+
+static EMPTY_TABLE: LuaValue<'static> = LuaValue::EMPTY_TABLE;
+
+/// The table at `key`, or an empty table if it is missing or not a table
+fn table<'v, 'a>(value: &'v LuaValue<'a>, key: &str) -> &'v LuaValue<'a> {
+    value
+        .get(key)
+        .filter(|val| val.as_table().is_some())
+        .unwrap_or(&EMPTY_TABLE)
+}
+
+/// The table at `key`, which is inserted if it is missing
+fn table_mut<'v, 'a>(value: &'v mut LuaValue<'a>, key: &str) -> Result<&'v mut LuaValue<'a>> {
+    let not_a_table = || Error::NotATable(key.to_owned());
+    let table = value
+        .as_table_mut()
+        .ok_or_else(not_a_table)?
+        .get_or_insert(key, LuaValue::EMPTY_TABLE);
+    match table {
+        LuaValue::Table(_) => Ok(table),
+        _ => Err(not_a_table()),
+    }
+}
+
+/// Fails like [`table_mut`] would if the entry at `key` exists but is not a table, without inserting anything
+fn check_table(value: &LuaValue, key: &str) -> Result<()> {
+    match value.get(key) {
+        None | Some(LuaValue::Table(_)) => Ok(()),
+        Some(_) => Err(Error::NotATable(key.to_owned())),
+    }
+}
+
+/// A table below `parent` which may be missing.
+///
+/// It reads as an empty table until it is first borrowed mutably, which inserts it along with the
+/// tables leading to it. Mutable views wrap it, so that they don't change the lua state until they write to it.
+#[derive(Debug)]
+pub struct TableMut<'v, V> {
+    parent: &'v mut V,
+    keys: Vec<&'static str>,
+}
+
+impl<'v, 'a, V: DerefMut<Target = LuaValue<'a>>> TableMut<'v, V> {
+    /// Fails if any of the tables along `keys` exists but is not a table
+    fn new(parent: &'v mut V, keys: &[&'static str]) -> Result<Self> {
+        let mut value: &LuaValue = parent;
+        if value.as_table().is_none() {
+            return Err(Error::NotATable(keys[0].to_owned()));
+        }
+        for key in keys {
+            check_table(value, key)?;
+            match value.get(key) {
+                Some(table) => value = table,
+                None => break,
+            }
+        }
+        Ok(TableMut {
+            parent,
+            keys: keys.to_vec(),
+        })
+    }
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Deref for TableMut<'_, V> {
+    type Target = LuaValue<'a>;
+
+    fn deref(&self) -> &LuaValue<'a> {
+        self.keys
+            .iter()
+            .try_fold(&**self.parent, |value, key| value.get(key))
+            .unwrap_or(&EMPTY_TABLE)
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> DerefMut for TableMut<'_, V> {
+    fn deref_mut(&mut self) -> &mut LuaValue<'a> {
+        self.keys.iter().fold(&mut **self.parent, |value, key| {
+            table_mut(value, key).expect("checked by TableMut::new")
+        })
+    }
+}
+
+/// Sets `key` of a table created by [`table`] or [`table_mut`], replacing whatever was there
+fn insert<'a>(table: &mut LuaValue<'a>, key: &str, value: LuaValue<'a>) {
+    let table = table.as_table_mut().expect("views only wrap tables");
+    *table.get_or_insert(key, LuaValue::Nil) = value;
+}
+
+/// Removes `key` from a table created by [`table`] or [`TableMut`], leaving a missing table missing
+fn remove<'a>(table: &mut impl DerefMut<Target = LuaValue<'a>>, key: &str) -> Option<LuaValue<'a>> {
+    table.get(key)?;
+    let table = table.as_table_mut().expect("views only wrap tables");
+    table.remove(key)
+}
+
+/// Entries of a table, or nothing if `value` isn't one
+fn entries<'v, 'a>(value: &'v LuaValue<'a>) -> std::slice::Iter<'v, (LuaValue<'a>, LuaValue<'a>)> {
+    value.as_table().map_or([].iter(), |table| table.0.iter())
+}
+
+fn string(str: &str) -> LuaValue<'static> {
+    LuaValue::String(str.to_owned().into())
+}
+
+/// View over the whole lua state of a savefile
+#[derive(Debug, Clone, Copy)]
+pub struct State<V> {
+    value: V,
+}
+
+impl<V> State<V> {
+    pub fn new(value: V) -> Self {
+        State { value }
+    }
+    pub fn into_inner(self) -> V {
+        self.value
+    }
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> State<V> {
+    pub fn value(&self) -> &LuaValue<'a> {
+        &self.value
+    }
+    fn game_state(&self) -> &LuaValue<'a> {
+        table(&self.value, "GameState")
+    }
+
+    pub fn resources(&self) -> Resources<&LuaValue<'a>> {
+        Resources {
+            value: table(self.game_state(), "Resources"),
+        }
+    }
+    /// The run in progress, or the last one while in the hub
+    pub fn current_run(&self) -> Run<&LuaValue<'a>> {
+        Run {
+            value: table(&self.value, "CurrentRun"),
+        }
+    }
+    pub fn run_history(&self) -> RunHistory<&LuaValue<'a>> {
+        RunHistory {
+            value: table(self.game_state(), "RunHistory"),
+        }
+    }
+    pub fn unlocks(&self) -> Unlocks<&LuaValue<'a>> {
+        Unlocks {
+            value: self.game_state(),
+        }
+    }
+    /// The Nocturnal Arms and their aspects
+    pub fn weapons(&self) -> Weapons<&LuaValue<'a>> {
+        Weapons {
+            value: self.game_state(),
+        }
+    }
+    pub fn relationships(&self) -> Relationships<&LuaValue<'a>> {
+        Relationships {
+            value: self.game_state(),
+        }
+    }
+    /// The arcana cards, which the game calls meta upgrades
+    pub fn cards(&self) -> Cards<&LuaValue<'a>> {
+        Cards {
+            value: self.game_state(),
+        }
+    }
+    /// The incantations of the crossroads cauldron
+    pub fn incantations(&self) -> Incantations<&LuaValue<'a>> {
+        Incantations {
+            value: self.game_state(),
+        }
+    }
+    /// The keepsakes, which characters give for their first gift
+    pub fn keepsakes(&self) -> Keepsakes<&LuaValue<'a>> {
+        Keepsakes {
+            value: self.game_state(),
+        }
+    }
+    pub fn familiars(&self) -> Familiars<&LuaValue<'a>> {
+        Familiars {
+            value: self.game_state(),
+        }
+    }
+    pub fn quests(&self) -> Quests<&LuaValue<'a>> {
+        Quests {
+            value: self.game_state(),
+        }
+    }
+    /// The vows of the Oath of the Unseen
+    pub fn vows(&self) -> Vows<&LuaValue<'a>> {
+        Vows {
+            value: self.game_state(),
+        }
+    }
+    pub fn settings(&self) -> Settings<&LuaValue<'a>> {
+        Settings {
+            value: self.game_state(),
+        }
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> State<V> {
+    pub fn value_mut(&mut self) -> &mut LuaValue<'a> {
+        &mut self.value
+    }
+    fn game_state_mut(&mut self) -> Result<TableMut<'_, V>> {
+        TableMut::new(&mut self.value, &["GameState"])
+    }
+
+    pub fn resources_mut(&mut self) -> Result<Resources<TableMut<'_, V>>> {
+        Ok(Resources {
+            value: TableMut::new(&mut self.value, &["GameState", "Resources"])?,
+        })
+    }
+    pub fn current_run_mut(&mut self) -> Result<Run<TableMut<'_, V>>> {
+        Ok(Run {
+            value: TableMut::new(&mut self.value, &["CurrentRun"])?,
+        })
+    }
+    pub fn run_history_mut(&mut self) -> Result<RunHistory<TableMut<'_, V>>> {
+        Ok(RunHistory {
+            value: TableMut::new(&mut self.value, &["GameState", "RunHistory"])?,
+        })
+    }
+    pub fn unlocks_mut(&mut self) -> Result<Unlocks<TableMut<'_, V>>> {
+        Ok(Unlocks {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn weapons_mut(&mut self) -> Result<Weapons<TableMut<'_, V>>> {
+        Ok(Weapons {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn relationships_mut(&mut self) -> Result<Relationships<TableMut<'_, V>>> {
+        Ok(Relationships {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn cards_mut(&mut self) -> Result<Cards<TableMut<'_, V>>> {
+        Ok(Cards {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn incantations_mut(&mut self) -> Result<Incantations<TableMut<'_, V>>> {
+        Ok(Incantations {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn keepsakes_mut(&mut self) -> Result<Keepsakes<TableMut<'_, V>>> {
+        Ok(Keepsakes {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn familiars_mut(&mut self) -> Result<Familiars<TableMut<'_, V>>> {
+        Ok(Familiars {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn quests_mut(&mut self) -> Result<Quests<TableMut<'_, V>>> {
+        Ok(Quests {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn vows_mut(&mut self) -> Result<Vows<TableMut<'_, V>>> {
+        Ok(Vows {
+            value: self.game_state_mut()?,
+        })
+    }
+    pub fn settings_mut(&mut self) -> Result<Settings<TableMut<'_, V>>> {
+        Ok(Settings {
+            value: self.game_state_mut()?,
+        })
+    }
+}
+
+/// View over a table of names mapped to `true`, like `GameState.WeaponsUnlocked`
+#[derive(Debug, Clone, Copy)]
+pub struct Set<V> {
+    value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Set<V> {
+    pub fn contains(&self, name: &str) -> bool {
+        self.value.get(name).and_then(LuaValue::as_bool) == Some(true)
+    }
+    /// Names mapped to `true`, in table order
+    pub fn iter<'v>(&'v self) -> impl Iterator<Item = &'v str>
+    where
+        'a: 'v,
+    {
+        entries(&self.value)
+            .filter(|(_, val)| val.as_bool() == Some(true))
+            .filter_map(|(key, _)| key.as_str())
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Set<V> {
+    pub fn insert(&mut self, name: &str) {
+        insert(&mut self.value, name, LuaValue::Bool(true));
+    }
+    /// Removes the entry, which the game treats the same as `false`
+    pub fn remove(&mut self, name: &str) -> bool {
+        remove(&mut self.value, name).is_some()
+    }
+    pub fn set(&mut self, name: &str, contained: bool) {
+        match contained {
+            true => self.insert(name),
+            false => _ = self.remove(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testdata::TEST_PROFILE_V18;
+    use super::{Error, QuestStatus, State};
+    use crate::saves::{LuaValue, Savefile, read_luatext};
+
+    #[test]
+    fn read_views() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+
+        assert_eq!(state.resources().get("CardUpgradePoints"), Some(1));
+        assert_eq!(state.resources().get("DoesNotExist"), None);
+        assert!(state.resources().iter().any(|(name, _)| name == "Money"));
+
+        assert_eq!(state.current_run().depth(), Some(33));
+        assert_eq!(state.run_history().len(), 9);
+        assert_eq!(
+            state.run_history().get(0).unwrap().killed_by(),
+            Some("FishmanMelee")
+        );
+
+        assert!(state.unlocks().weapons().contains("WeaponAxe"));
+        assert!(!state.unlocks().weapons().contains("WeaponLob"));
+        assert_eq!(
+            state.quests().status("QuestUnlockDagger"),
+            QuestStatus::CashedOut
+        );
+        assert!(state.quests().viewed("QuestUnlockDagger"));
+        assert_eq!(state.relationships().level("ApolloUpgrade"), 1);
+        assert!(
+            !state
+                .relationships()
+                .names()
+                .any(|name| name.starts_with("Default"))
+        );
+        assert_eq!(state.settings().easy_mode_level(), Some(0));
+        assert!(state.settings().flags().contains("HasUpgradedCards"));
+    }
+
+    #[test]
+    fn write_lossless() {
+        let (savefile, original) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut lua_state = original.clone();
+        let mut state = State::new(&mut lua_state);
+
+        state
+            .resources_mut()
+            .unwrap()
+            .set("CardUpgradePoints", 100)
+            .unwrap();
+        state
+            .unlocks_mut()
+            .unwrap()
+            .weapons_mut()
+            .unwrap()
+            .insert("WeaponLob");
+        state
+            .quests_mut()
+            .unwrap()
+            .set_status("QuestPetFrog", QuestStatus::CashedOut)
+            .unwrap();
+        assert_eq!(
+            lua_state.get_path("GameState.Resources.CardUpgradePoints"),
+            Some(&LuaValue::Number(100.0))
+        );
+
+        let mut data = Vec::new();
+        savefile.serialize(&mut data, &lua_state).unwrap();
+        let (_, reparsed) = Savefile::parse(&data).unwrap();
+        assert!(reparsed.canonical_eq(&lua_state));
+
+        // undoing the edits leaves only the known fields changed
+        let mut state = State::new(&mut lua_state);
+        state
+            .resources_mut()
+            .unwrap()
+            .set("CardUpgradePoints", 1)
+            .unwrap();
+        state
+            .unlocks_mut()
+            .unwrap()
+            .weapons_mut()
+            .unwrap()
+            .remove("WeaponLob");
+        state
+            .quests_mut()
+            .unwrap()
+            .set_status("QuestPetFrog", QuestStatus::Unlocked)
+            .unwrap();
+        assert!(lua_state.canonical_eq(&original));
+    }
+
+    #[test]
+    fn missing_tables() {
+        let mut lua_state = read_luatext("{}").unwrap();
+        let mut state = State::new(&mut lua_state);
+        assert_eq!(state.resources().iter().count(), 0);
+        assert_eq!(state.run_history().len(), 0);
+
+        state.resources_mut().unwrap().set("Money", 5).unwrap();
+        state.settings_mut().unwrap().set_easy_mode_level(2);
+        assert_eq!(
+            lua_state,
+            read_luatext("{ GameState = { EasyModeLevel = 2, Resources = { Money = 5 } } }")
+                .unwrap()
+        );
+
+        let mut lua_state = read_luatext("{ GameState = 1 }").unwrap();
+        assert!(State::new(&mut lua_state).resources_mut().is_err());
+    }
+
+    #[test]
+    fn failed_calls_leave_tables_missing() {
+        let mut lua_state = read_luatext("{}").unwrap();
+        let mut state = State::new(&mut lua_state);
+
+        let resources = state.resources_mut().unwrap();
+        assert_eq!(resources.get("Money"), None);
+        assert!(matches!(
+            state.cards_mut().unwrap().unlock("DoesNotExist"),
+            Err(Error::UnknownCard(_))
+        ));
+        state.keepsakes_mut().unwrap().unequip();
+        state
+            .settings_mut()
+            .unwrap()
+            .flags_mut()
+            .unwrap()
+            .remove("EasyMode");
+        assert!(state.run_history_mut().unwrap().get_mut(0).is_none());
+        assert_eq!(lua_state, read_luatext("{}").unwrap());
+
+        let mut lua_state =
+            read_luatext("{ GameState = { WorldUpgrades = {}, WorldUpgradesViewed = 1 } }")
+                .unwrap();
+        let original = lua_state.clone();
+        let mut state = State::new(&mut lua_state);
+        let result = state.incantations_mut().unwrap().cast("WorldUpgradeMarket");
+        assert!(matches!(result, Err(Error::NotATable(_))));
+        assert_eq!(lua_state, original);
+    }
+}
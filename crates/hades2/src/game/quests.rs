@@ -0,0 +1,346 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, Set, TableMut, check_table, entries, insert, remove, string, table};
+use crate::saves::LuaValue;
+
+/// Entry of the [`QUESTS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuestInfo {
+    /// Key in `GameState.QuestStatus`
+    pub name: &'static str,
+    /// Title in the Fated List, like `The Sister Blades`
+    pub display_name: &'static str,
+    /// Table of `GameState` which records the progress, if it is tracked by this crate
+    pub record: Option<&'static str>,
+    /// Keys of `record` which the quest asks for
+    pub required: &'static [&'static str],
+}
+
+const fn quest(name: &'static str, display_name: &'static str) -> QuestInfo {
+    QuestInfo {
+        name,
+        display_name,
+        record: None,
+        required: &[],
+    }
+}
+
+const fn tracked(
+    name: &'static str,
+    display_name: &'static str,
+    record: &'static str,
+    required: &'static [&'static str],
+) -> QuestInfo {
+    QuestInfo {
+        name,
+        display_name,
+        record: Some(record),
+        required,
+    }
+}
+
+/// The quests of the Fated List of Minor Prophecies.
+///
+/// Only the first six have their progress tracked, so [`Quests::progress`] is `None` for most of them.
+/// The others are recorded by the game in ways which change between game versions.
+#[rustfmt::skip]
+pub const QUESTS: &[QuestInfo] = &[
+    tracked("QuestUnlockDagger", "The Sister Blades", "WeaponsUnlocked", &["WeaponDagger"]),
+    tracked("QuestBeatHecate", "The Witch of the Crossroads", "EnemyKills", &["Hecate"]),
+    tracked("QuestFirstUnderworldClear", "The Titan of Time", "EnemyKills", &["Chronos"]),
+    tracked("QuestToolsUnlocks", "Gathering Tools", "WeaponsUnlocked", &["ToolPickaxe", "ToolExorcismBook", "ToolShovel", "ToolFishingRod"]),
+    tracked("QuestToolsUpgrades", "Improved Tools", "WeaponsUnlocked", &["ToolPickaxe2", "ToolExorcismBook2", "ToolShovel2", "ToolFishingRod2"]),
+    tracked("QuestUnlockAllWeapons", "The Nocturnal Arms", "WeaponsUnlocked", &["WeaponDagger", "WeaponTorch", "WeaponAxe", "WeaponLob", "WeaponSuit"]),
+    quest("QuestUnlockAllCards", "The Arcana"),
+    quest("QuestPetFrog", "Familiar Friend"),
+    quest("QuestUnlockMoros", "The Lord of Doom"),
+    quest("QuestUnlockBountyBoard", "The Pitch-Black Stone"),
+    quest("QuestCodexSmall", "Hidden Knowledge"),
+    quest("QuestCauldronSpellsSmall", "Crossroads Incantations"),
+    quest("QuestGiftNectar", "Nectar Gifts"),
+    quest("QuestMemLevel10", "Psyche Unbound"),
+    quest("QuestMeetOlympians", "The Olympians"),
+    quest("QuestSpendCharonPoints", "Charon's Patronage"),
+    quest("QuestPurchasePinnedItems", "Pinned Requirements"),
+    quest("QuestMaxCardUpgrade", "Arcana Mastery"),
+    quest("QuestMaxWeaponUpgrade", "Aspect Mastery"),
+    quest("QuestMiniBossKills", "Guardian Slayer"),
+    quest("QuestClearBountiesSmall", "Trials of Night"),
+    quest("QuestRescueFates", "The Three Fates"),
+    quest("QuestHelpNarcissusAndEcho", "Narcissus and Echo"),
+    quest("QuestShadeMercRecruits", "Shade Allies"),
+    quest("QuestWellShopItems", "Well of Charon Wares"),
+    quest("QuestDarkSorceries", "Dark Sorceries"),
+    quest("QuestBeatHecateWithoutArcana", "Unaided Victory"),
+    quest("QuestBeatChronosWithArcana", "Full Arcana Victory"),
+    quest("QuestSynergyUpgrades", "Duo Blessings"),
+    quest("QuestEchoUpgrades", "Echo's Blessings"),
+    quest("QuestHermesUpgrades", "Hermes' Blessings"),
+    quest("QuestArachneUpgrades", "Arachne's Dresses"),
+    quest("QuestIcarusUpgrades", "Icarus' Blessings"),
+    quest("QuestHadesUpgrades", "Hades' Blessings"),
+    quest("QuestChaosBlessings", "Chaos' Blessings"),
+    quest("QuestChaosCurses", "Chaos' Curses"),
+    quest("QuestMedeaCurses", "Medea's Curses"),
+    quest("QuestZeusUpgrades", "Zeus' Blessings"),
+    quest("QuestHeraUpgrades", "Hera's Blessings"),
+    quest("QuestPoseidonUpgrades", "Poseidon's Blessings"),
+    quest("QuestDemeterUpgrades", "Demeter's Blessings"),
+    quest("QuestApolloUpgrades", "Apollo's Blessings"),
+    quest("QuestAphroditeUpgrades", "Aphrodite's Blessings"),
+    quest("QuestHephaestusUpgrades", "Hephaestus' Blessings"),
+    quest("QuestHestiaUpgrades", "Hestia's Blessings"),
+    quest("QuestStaffHammerUpgrades", "Witch's Staff Hammers"),
+    quest("QuestDaggerHammerUpgrades", "Sister Blades Hammers"),
+    quest("QuestTorchHammerUpgrades", "Umbral Flames Hammers"),
+    quest("QuestAxeHammerUpgrades", "Moonstone Axe Hammers"),
+];
+
+/// Looks up a quest in the [`QUESTS`] catalogue by its key in `GameState.QuestStatus`
+pub fn quest_info(name: &str) -> Option<&'static QuestInfo> {
+    QUESTS.iter().find(|info| info.name == name)
+}
+
+/// Status of a quest in the Fated List
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuestStatus {
+    /// Not revealed yet, so missing from `QuestStatus`
+    Locked,
+    /// Revealed, but not fulfilled yet
+    Unlocked,
+    /// Fulfilled, with its reward waiting to be claimed
+    Complete,
+    /// Fulfilled and its reward claimed
+    CashedOut,
+}
+
+impl QuestStatus {
+    /// The value in `QuestStatus`, or `None` for [`QuestStatus::Locked`]
+    pub fn as_str(self) -> Option<&'static str> {
+        match self {
+            QuestStatus::Locked => None,
+            QuestStatus::Unlocked => Some("Unlocked"),
+            QuestStatus::Complete => Some("Complete"),
+            QuestStatus::CashedOut => Some("CashedOut"),
+        }
+    }
+    /// Parses a value of `QuestStatus`, where unknown values count as [`QuestStatus::Unlocked`]
+    fn from_value(value: Option<&LuaValue>) -> QuestStatus {
+        match value.and_then(LuaValue::as_str) {
+            None => QuestStatus::Locked,
+            Some("Complete") => QuestStatus::Complete,
+            Some("CashedOut") => QuestStatus::CashedOut,
+            Some(_) => QuestStatus::Unlocked,
+        }
+    }
+    pub fn is_fulfilled(self) -> bool {
+        matches!(self, QuestStatus::Complete | QuestStatus::CashedOut)
+    }
+}
+
+/// How much of a quest's requirements the records fulfill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuestProgress {
+    pub current: u32,
+    pub required: u32,
+}
+
+impl QuestProgress {
+    pub fn is_done(&self) -> bool {
+        self.current >= self.required
+    }
+}
+
+/// Status and progress of a single quest of the [`QUESTS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuestState {
+    pub info: &'static QuestInfo,
+    pub status: QuestStatus,
+    /// `None` unless the [`QUESTS`] catalogue tracks the quest, which only a few are
+    pub progress: Option<QuestProgress>,
+}
+
+/// View over `GameState.QuestStatus` and `GameState.QuestsViewed`, the Fated List of Minor Prophecies.
+///
+/// The status of every quest is available, but progress only for the few quests the [`QUESTS`] catalogue tracks.
+#[derive(Debug, Clone, Copy)]
+pub struct Quests<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Quests<V> {
+    pub fn status(&self, name: &str) -> QuestStatus {
+        QuestStatus::from_value(table(&self.value, "QuestStatus").get(name))
+    }
+    /// All revealed quests and their status, in table order, including ones missing from [`QUESTS`]
+    pub fn revealed<'v>(&'v self) -> impl Iterator<Item = (&'v str, QuestStatus)>
+    where
+        'a: 'v,
+    {
+        entries(table(&self.value, "QuestStatus"))
+            .filter_map(|(key, val)| Some((key.as_str()?, QuestStatus::from_value(Some(val)))))
+    }
+    /// Progress of a quest whose requirements are tracked by the [`QUESTS`] catalogue.
+    ///
+    /// A requirement counts as fulfilled when its key in the record is `true` or a positive number.
+    pub fn progress(&self, name: &str) -> Option<QuestProgress> {
+        let info = quest_info(name)?;
+        let record = table(&self.value, info.record?);
+        let current = info
+            .required
+            .iter()
+            .filter(|key| match record.get(key) {
+                Some(LuaValue::Bool(val)) => *val,
+                Some(LuaValue::Number(val)) => *val > 0.0,
+                _ => false,
+            })
+            .count();
+        Some(QuestProgress {
+            current: current as u32,
+            required: info.required.len() as u32,
+        })
+    }
+    /// Every quest of the [`QUESTS`] catalogue, in its order
+    pub fn iter(&self) -> impl Iterator<Item = QuestState> + '_ {
+        QUESTS.iter().map(|info| QuestState {
+            info,
+            status: self.status(info.name),
+            progress: self.progress(info.name),
+        })
+    }
+    pub fn viewed(&self, name: &str) -> bool {
+        let viewed = Set {
+            value: table(&self.value, "QuestsViewed"),
+        };
+        viewed.contains(name)
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Quests<V> {
+    /// Sets the status of a quest, where [`QuestStatus::Locked`] removes it from the Fated List
+    pub fn set_status(&mut self, name: &str, status: QuestStatus) -> Result<()> {
+        let mut quests = TableMut::new(&mut self.value, &["QuestStatus"])?;
+        match status.as_str() {
+            Some(status) => insert(&mut quests, name, string(status)),
+            None => _ = remove(&mut quests, name),
+        }
+        Ok(())
+    }
+    pub fn set_viewed(&mut self, name: &str, viewed: bool) -> Result<()> {
+        let mut set = Set {
+            value: TableMut::new(&mut self.value, &["QuestsViewed"])?,
+        };
+        set.set(name, viewed);
+        Ok(())
+    }
+
+    /// Marks a quest of the [`QUESTS`] catalogue as complete, so its reward can be claimed in the game.
+    ///
+    /// The game only looks at the status once a quest is complete, so the records behind its
+    /// progress are left alone. Returns `false` if the quest was complete already.
+    pub fn complete(&mut self, name: &str) -> Result<bool> {
+        quest_info(name).ok_or_else(|| Error::UnknownQuest(name.to_owned()))?;
+        if self.status(name).is_fulfilled() {
+            return Ok(false);
+        }
+        self.set_status(name, QuestStatus::Complete)?;
+        Ok(true)
+    }
+    /// Puts a quest of the [`QUESTS`] catalogue back into the Fated List as unlocked and not viewed.
+    ///
+    /// A quest whose reward was already claimed can then be fulfilled and claimed again, so it is refused
+    /// unless `allow_reclaim` is set. The claimed reward is not taken back either way.
+    pub fn reset(&mut self, name: &str, allow_reclaim: bool) -> Result<()> {
+        quest_info(name).ok_or_else(|| Error::UnknownQuest(name.to_owned()))?;
+        if self.status(name) == QuestStatus::CashedOut && !allow_reclaim {
+            return Err(Error::QuestCashedOut(name.to_owned()));
+        }
+        check_table(&self.value, "QuestsViewed")?;
+        self.set_status(name, QuestStatus::Unlocked)?;
+        self.set_viewed(name, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QUESTS, QuestProgress, QuestStatus};
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::game::{Error, State};
+    use crate::saves::Savefile;
+
+    #[test]
+    fn catalogue_covers_saves() {
+        for data in [TEST_PROFILE_V17, TEST_PROFILE_V18] {
+            let (_, lua_state) = Savefile::parse(data).unwrap();
+            let state = State::new(&lua_state);
+            let quests = state.quests();
+            for (name, status) in quests.revealed() {
+                assert!(QUESTS.iter().any(|info| info.name == name), "{name}");
+                assert_eq!(quests.status(name), status);
+            }
+            // the progress of fulfilled quests is complete
+            for quest in quests.iter() {
+                if quest.status.is_fulfilled() {
+                    assert!(quest.progress.is_none_or(|p| p.is_done()), "{quest:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn progress() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        let quests = state.quests();
+        assert_eq!(quests.status("QuestToolsUnlocks"), QuestStatus::Unlocked);
+        assert_eq!(
+            quests.progress("QuestToolsUnlocks"),
+            Some(QuestProgress {
+                current: 3,
+                required: 4
+            })
+        );
+        assert_eq!(quests.status("QuestHeraUpgrades"), QuestStatus::Locked);
+        assert_eq!(quests.progress("QuestHeraUpgrades"), None);
+        assert_eq!(
+            quests
+                .iter()
+                .filter(|quest| quest.progress.is_some())
+                .count(),
+            6
+        );
+    }
+
+    #[test]
+    fn complete_and_reset() {
+        let (_, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut quests = state.quests_mut().unwrap();
+
+        assert!(quests.complete("QuestToolsUnlocks").unwrap());
+        assert_eq!(quests.status("QuestToolsUnlocks"), QuestStatus::Complete);
+        assert!(!quests.complete("QuestUnlockDagger").unwrap());
+        assert_eq!(quests.status("QuestUnlockDagger"), QuestStatus::CashedOut);
+        assert!(quests.complete("QuestHeraUpgrades").unwrap());
+
+        assert!(matches!(
+            quests.reset("QuestUnlockDagger", false),
+            Err(Error::QuestCashedOut(_))
+        ));
+        assert_eq!(quests.status("QuestUnlockDagger"), QuestStatus::CashedOut);
+        quests.reset("QuestToolsUnlocks", false).unwrap();
+        assert_eq!(quests.status("QuestToolsUnlocks"), QuestStatus::Unlocked);
+        quests.reset("QuestUnlockDagger", true).unwrap();
+        assert_eq!(quests.status("QuestUnlockDagger"), QuestStatus::Unlocked);
+        assert!(!quests.viewed("QuestUnlockDagger"));
+
+        quests
+            .set_status("QuestHeraUpgrades", QuestStatus::Locked)
+            .unwrap();
+        assert_eq!(quests.status("QuestHeraUpgrades"), QuestStatus::Locked);
+        assert!(matches!(
+            quests.complete("Unknown"),
+            Err(Error::UnknownQuest(_))
+        ));
+    }
+}
@@ -0,0 +1,500 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{
+    Error, Result, Set, TableMut, check_table, entries, insert, remove, string, table, table_mut,
+};
+use crate::saves::{LuaTable, LuaValue};
+
+/// Entry of the [`RELATIONSHIPS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelationshipInfo {
+    /// Key in `GameState.Gift` and `GameState.GiftRecord`
+    pub name: &'static str,
+    /// Name of the character, like `Aphrodite` for `AphroditeUpgrade`
+    pub display_name: &'static str,
+    /// Hearts at which the bond is forged
+    pub max_level: u32,
+    /// Prefix and number of the text lines played for gifts, like `HecateGift01` to `HecateGift06`,
+    /// or `None` if they aren't known
+    pub gift_text_lines: Option<(&'static str, u32)>,
+}
+
+const fn relationship(
+    name: &'static str,
+    display_name: &'static str,
+    max_level: u32,
+    gift_text_lines: Option<(&'static str, u32)>,
+) -> RelationshipInfo {
+    RelationshipInfo {
+        name,
+        display_name,
+        max_level,
+        gift_text_lines,
+    }
+}
+
+/// The characters that accept gifts, gods first.
+///
+/// Gods only take nectar, while the other characters take bath salts and ambrosia for their last hearts.
+/// Nectar gifts play the gift text lines in order, but only once the game's requirements for the next line
+/// are met, so there can be fewer lines than gifts: Nemesis took 3 gifts in the v18 save and played 2 lines.
+/// The lines of Eris are named irregularly, like `ErisGift01_B`, and those of Athena and Dionysus don't
+/// appear in any save.
+#[rustfmt::skip]
+pub const RELATIONSHIPS: &[RelationshipInfo] = &[
+    relationship("AphroditeUpgrade", "Aphrodite", 4, Some(("Aphrodite", 4))),
+    relationship("ApolloUpgrade", "Apollo", 4, Some(("Apollo", 4))),
+    relationship("DemeterUpgrade", "Demeter", 4, Some(("Demeter", 4))),
+    relationship("HephaestusUpgrade", "Hephaestus", 4, Some(("Hephaestus", 4))),
+    relationship("HeraUpgrade", "Hera", 4, Some(("Hera", 4))),
+    relationship("HermesUpgrade", "Hermes", 4, Some(("Hermes", 4))),
+    relationship("HestiaUpgrade", "Hestia", 4, Some(("Hestia", 4))),
+    relationship("PoseidonUpgrade", "Poseidon", 4, Some(("Poseidon", 4))),
+    relationship("ZeusUpgrade", "Zeus", 4, Some(("Zeus", 4))),
+    relationship("SpellDrop", "Selene", 4, Some(("Selene", 4))),
+    relationship("TrialUpgrade", "Chaos", 5, Some(("Chaos", 5))),
+    relationship("NPC_Arachne_01", "Arachne", 11, Some(("Arachne", 6))),
+    relationship("NPC_Artemis_01", "Artemis", 11, Some(("Artemis", 6))),
+    relationship("NPC_Athena_01", "Athena", 4, None),
+    relationship("NPC_Charon_01", "Charon", 11, Some(("Charon", 6))),
+    relationship("NPC_Circe_01", "Circe", 11, Some(("Circe", 6))),
+    relationship("NPC_Dionysus_01", "Dionysus", 4, None),
+    relationship("NPC_Dora_01", "Dora", 11, Some(("Dora", 6))),
+    relationship("NPC_Echo_01", "Echo", 11, Some(("Echo", 6))),
+    relationship("NPC_Eris_01", "Eris", 11, None),
+    relationship("NPC_Hecate_01", "Hecate", 11, Some(("Hecate", 6))),
+    relationship("NPC_Heracles_01", "Heracles", 11, Some(("Heracles", 6))),
+    relationship("NPC_Hypnos_01", "Hypnos", 11, Some(("Hypnos", 6))),
+    relationship("NPC_Icarus_01", "Icarus", 11, Some(("Icarus", 6))),
+    relationship("NPC_Medea_01", "Medea", 11, Some(("Medea", 6))),
+    relationship("NPC_Moros_01", "Moros", 11, Some(("Moros", 6))),
+    relationship("NPC_Narcissus_01", "Narcissus", 11, Some(("Narcissus", 6))),
+    relationship("NPC_Nemesis_01", "Nemesis", 11, Some(("Nemesis", 6))),
+    relationship("NPC_Odysseus_01", "Odysseus", 11, Some(("Odysseus", 6))),
+    relationship("NPC_Skelly_01", "Schelemeus", 11, Some(("Skelly", 6))),
+];
+
+/// Looks up a character in the [`RELATIONSHIPS`] catalogue by its key in `GameState.Gift`
+pub fn relationship_info(name: &str) -> Option<&'static RelationshipInfo> {
+    RELATIONSHIPS.iter().find(|info| info.name == name)
+}
+
+/// The resource gifted for the heart at `level`, when raising a relationship
+fn gift_for_level(info: &RelationshipInfo, level: u32) -> &'static str {
+    if info.max_level <= 5 {
+        return "GiftPoints";
+    }
+    match level {
+        ..=6 => "GiftPoints",
+        7..=9 => "GiftPointsRare",
+        _ => "GiftPointsEpic",
+    }
+}
+
+/// The `n`th gift text line of a character, counting from 1
+fn gift_text_line(prefix: &str, n: u32) -> String {
+    format!("{prefix}Gift{n:02}")
+}
+
+/// Relationship with a single character
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relationship<'v> {
+    pub name: &'v str,
+    /// Number of accepted gifts, which the game shows as hearts
+    pub level: u32,
+    /// Accepted gifts per resource, like `GiftPoints` for nectar
+    pub gifts: Vec<(&'v str, u32)>,
+    /// Whether all hearts of the [`RELATIONSHIPS`] catalogue are filled
+    pub bond_forged: bool,
+}
+
+/// View over the relationships with every character that can receive gifts.
+///
+/// Characters are named by their gift data, like `NPC_Hecate_01` or `ApolloUpgrade` for the gods.
+/// Every accepted gift is recorded in order in `GameState.GiftRecord`, along with a count per resource,
+/// and `GameState.GiftResourceRecord` sums up all gifts ever given. The text line played for a gift is marked
+/// in `GameState.TextLinesRecord`, and since v18 also listed in order in `GameState.GiftTextLinesOrderRecord`.
+/// All of them are kept in sync when changing levels.
+#[derive(Debug, Clone, Copy)]
+pub struct Relationships<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Relationships<V> {
+    /// Characters with a relationship entry, skipping the game's `Default*` templates
+    pub fn names<'v>(&'v self) -> impl Iterator<Item = &'v str>
+    where
+        'a: 'v,
+    {
+        entries(table(&self.value, "Gift"))
+            .filter_map(|(key, _)| key.as_str())
+            .filter(|name| !name.starts_with("Default"))
+    }
+    /// All characters with a relationship entry, see [`Relationships::names`]
+    pub fn iter<'v>(&'v self) -> impl Iterator<Item = Relationship<'v>>
+    where
+        'a: 'v,
+    {
+        self.names().map(|name| Relationship {
+            name,
+            level: self.level(name),
+            gifts: self.gifts(name).collect(),
+            bond_forged: self.bond_forged(name),
+        })
+    }
+
+    fn record(&self, name: &str) -> &LuaValue<'a> {
+        table(table(&self.value, "GiftRecord"), name)
+    }
+    /// Number of gifts accepted, which the game shows as hearts
+    pub fn level(&self, name: &str) -> u32 {
+        entries(self.record(name))
+            .filter(|(key, val)| key.as_number().is_some() && val.as_str().is_some())
+            .count() as u32
+    }
+    /// Accepted gifts per resource, in table order
+    pub fn gifts<'v>(&'v self, name: &str) -> impl Iterator<Item = (&'v str, u32)>
+    where
+        'a: 'v,
+    {
+        entries(self.record(name)).filter_map(|(key, val)| Some((key.as_str()?, val.as_u32()?)))
+    }
+    /// Whether the character is at the highest level of the [`RELATIONSHIPS`] catalogue
+    pub fn bond_forged(&self, name: &str) -> bool {
+        relationship_info(name).is_some_and(|info| self.level(name) >= info.max_level)
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Relationships<V> {
+    /// Fails if a table [`Relationships::set_level`] writes to is not a table, before anything is written
+    fn check_tables(&self, name: &str) -> Result<()> {
+        for key in [
+            "GiftResourceRecord",
+            "TextLinesRecord",
+            "GiftTextLinesOrderRecord",
+            "Gift",
+            "GiftRecord",
+        ] {
+            check_table(&self.value, key)?;
+        }
+        check_table(table(&self.value, "Gift"), name)
+    }
+
+    /// Sets the number of hearts by adding or removing the latest gifts, along with their text lines.
+    ///
+    /// New gifts are nectar, or bath salts and ambrosia for the last hearts of characters other than gods.
+    /// Each of them plays the next gift text line after those already recorded, and lines beyond the new
+    /// number of hearts are removed.
+    /// The character must be in the [`RELATIONSHIPS`] catalogue with known gift text lines, and the level
+    /// may not exceed its maximum.
+    pub fn set_level(&mut self, name: &str, level: u32) -> Result<()> {
+        let info =
+            relationship_info(name).ok_or_else(|| Error::UnknownCharacter(name.to_owned()))?;
+        if level > info.max_level {
+            return Err(Error::InvalidHeartLevel {
+                name: name.to_owned(),
+                level,
+                max_level: info.max_level,
+            });
+        }
+        let (prefix, count) = info
+            .gift_text_lines
+            .ok_or_else(|| Error::UnknownGiftTextLines(name.to_owned()))?;
+        self.check_tables(name)?;
+
+        let mut gifts: Vec<String> = entries(self.record(name))
+            .filter(|(key, _)| key.as_number().is_some())
+            .filter_map(|(_, val)| val.as_str().map(str::to_owned))
+            .collect();
+        let old_level = gifts.len() as u32;
+        let removed = gifts.split_off(gifts.len().min(level as usize));
+        let added: Vec<String> = (gifts.len() as u32 + 1..=level)
+            .map(|level| gift_for_level(info, level).to_owned())
+            .collect();
+        gifts.extend(added.iter().cloned());
+
+        let totals = table_mut(&mut self.value, "GiftResourceRecord")?;
+        for (gifts, sign) in [(&removed, -1.0), (&added, 1.0)] {
+            for gift in gifts {
+                let total = totals
+                    .get(gift)
+                    .and_then(LuaValue::as_number)
+                    .unwrap_or(0.0);
+                insert(totals, gift, LuaValue::Number((total + sign).max(0.0)));
+            }
+        }
+
+        let recorded = Set {
+            value: table(&self.value, "TextLinesRecord"),
+        };
+        let played_count = (1..=count)
+            .take_while(|&n| recorded.contains(&gift_text_line(prefix, n)))
+            .count() as u32;
+        let removed_lines: Vec<String> = (level + 1..=count)
+            .map(|n| gift_text_line(prefix, n))
+            .filter(|line| recorded.contains(line))
+            .collect();
+        let last_added = (played_count + level.saturating_sub(old_level)).min(level.min(count));
+        let added_lines: Vec<String> = (played_count + 1..=last_added)
+            .map(|n| gift_text_line(prefix, n))
+            .collect();
+
+        let mut played = Set {
+            value: table_mut(&mut self.value, "TextLinesRecord")?,
+        };
+        for line in &removed_lines {
+            played.remove(line);
+        }
+        for line in &added_lines {
+            played.insert(line);
+        }
+        // older saves have no order record, which the game then doesn't need
+        if self.value.get("GiftTextLinesOrderRecord").is_some() {
+            let orders = table_mut(&mut self.value, "GiftTextLinesOrderRecord")?;
+            let mut order: Vec<String> = entries(table(orders, name))
+                .filter(|(key, _)| key.as_number().is_some())
+                .filter_map(|(_, val)| val.as_str().map(str::to_owned))
+                .filter(|line| !removed_lines.contains(line))
+                .collect();
+            for line in added_lines {
+                if !order.contains(&line) {
+                    order.push(line);
+                }
+            }
+            if order.is_empty() {
+                orders.as_table_mut().unwrap().remove(name);
+            } else {
+                let order = order
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| (LuaValue::Number((i + 1) as f64), string(line)))
+                    .collect();
+                insert(orders, name, LuaValue::Table(LuaTable(order)));
+            }
+        }
+
+        let gift = table_mut(table_mut(&mut self.value, "Gift")?, name)?;
+        if gift.get("Value").is_none() {
+            insert(gift, "Value", LuaValue::Number(0.0));
+        }
+
+        let mut records = TableMut::new(&mut self.value, &["GiftRecord"])?;
+        if gifts.is_empty() {
+            remove(&mut records, name);
+            return Ok(());
+        }
+        let mut record = LuaTable(Vec::new());
+        for (i, gift) in gifts.iter().enumerate() {
+            record
+                .0
+                .push((LuaValue::Number((i + 1) as f64), string(gift)));
+        }
+        for gift in &gifts {
+            match record.get_mut(gift) {
+                Some(count) => *count = LuaValue::Number(count.as_number().unwrap_or(0.0) + 1.0),
+                None => record.0.push((string(gift), LuaValue::Number(1.0))),
+            }
+        }
+        record.sort();
+        insert(&mut records, name, LuaValue::Table(record));
+        Ok(())
+    }
+
+    /// Forges the bond with every character of the [`RELATIONSHIPS`] catalogue,
+    /// skipping those whose gift text lines are unknown
+    pub fn max_out(&mut self) -> Result<()> {
+        for info in RELATIONSHIPS {
+            self.check_tables(info.name)?;
+        }
+        for info in RELATIONSHIPS {
+            if info.gift_text_lines.is_some() && self.level(info.name) < info.max_level {
+                self.set_level(info.name, info.max_level)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RELATIONSHIPS, relationship_info};
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::game::{Error, State};
+    use crate::saves::{LuaValue, Savefile, read_luatext};
+
+    #[test]
+    fn read_relationships() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        let relationships = state.relationships();
+
+        assert!(
+            relationships
+                .names()
+                .all(|name| relationship_info(name).is_some())
+        );
+        assert_eq!(relationships.level("NPC_Echo_01"), 3);
+        assert_eq!(relationships.level("NPC_Odysseus_01"), 0);
+        assert_eq!(
+            relationships.gifts("NPC_Hecate_01").collect::<Vec<_>>(),
+            [("GiftPoints", 2)]
+        );
+        let nemesis = relationships
+            .iter()
+            .find(|relationship| relationship.name == "NPC_Nemesis_01")
+            .unwrap();
+        assert_eq!(nemesis.level, 3);
+        assert!(!nemesis.bond_forged);
+    }
+
+    #[test]
+    fn set_level() {
+        let mut lua_state = read_luatext(
+            r#"{ GameState = {
+                Gift = { ZeusUpgrade = { Value = 0 }, NPC_New_01 = { Value = 0 } },
+                GiftRecord = { ZeusUpgrade = { "GiftPoints", "GiftPoints", GiftPoints = 2 } },
+                GiftResourceRecord = { GiftPoints = 2 },
+                TextLinesRecord = { ZeusGift01 = true, ZeusGift02 = true, ZeusPostGift01 = true },
+            } }"#,
+        )
+        .unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut relationships = state.relationships_mut().unwrap();
+
+        relationships.set_level("NPC_Dora_01", 8).unwrap();
+        relationships.set_level("ZeusUpgrade", 1).unwrap();
+        assert!(matches!(
+            relationships.set_level("ZeusUpgrade", 5),
+            Err(Error::InvalidHeartLevel { max_level: 4, .. })
+        ));
+        assert!(matches!(
+            relationships.set_level("NPC_New_01", 1),
+            Err(Error::UnknownCharacter(_))
+        ));
+        assert!(matches!(
+            relationships.set_level("NPC_Eris_01", 1),
+            Err(Error::UnknownGiftTextLines(_))
+        ));
+        assert_eq!(relationships.level("NPC_Dora_01"), 8);
+        assert_eq!(
+            relationships.gifts("NPC_Dora_01").collect::<Vec<_>>(),
+            [("GiftPoints", 6), ("GiftPointsRare", 2)]
+        );
+        assert!(!relationships.bond_forged("NPC_Dora_01"));
+
+        let expected = read_luatext(
+            r#"{ GameState = {
+                Gift = { NPC_Dora_01 = { Value = 0 }, NPC_New_01 = { Value = 0 }, ZeusUpgrade = { Value = 0 } },
+                GiftRecord = {
+                    NPC_Dora_01 = { "GiftPoints", "GiftPoints", "GiftPoints", "GiftPoints", "GiftPoints", "GiftPoints",
+                        "GiftPointsRare", "GiftPointsRare", GiftPoints = 6, GiftPointsRare = 2 },
+                    ZeusUpgrade = { "GiftPoints", GiftPoints = 1 },
+                },
+                GiftResourceRecord = { GiftPoints = 7, GiftPointsRare = 2 },
+                TextLinesRecord = {
+                    DoraGift01 = true, DoraGift02 = true, DoraGift03 = true,
+                    DoraGift04 = true, DoraGift05 = true, DoraGift06 = true,
+                    ZeusGift01 = true, ZeusPostGift01 = true,
+                },
+            } }"#,
+        )
+        .unwrap();
+        assert!(lua_state.canonical_eq(&expected));
+    }
+
+    #[test]
+    fn set_level_fewer_lines_than_gifts() {
+        let (_, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut relationships = state.relationships_mut().unwrap();
+        assert_eq!(relationships.level("NPC_Nemesis_01"), 3);
+        relationships.set_level("NPC_Nemesis_01", 4).unwrap();
+
+        let game_state = lua_state.get("GameState").unwrap();
+        let lines = game_state.get("TextLinesRecord").unwrap();
+        let played = (1..=6)
+            .filter(|n| lines.get(&format!("NemesisGift{n:02}")).is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(played, [1, 2, 3]);
+        let order = game_state
+            .get("GiftTextLinesOrderRecord")
+            .and_then(|orders| orders.get("NPC_Nemesis_01"))
+            .and_then(LuaValue::as_table)
+            .unwrap();
+        assert_eq!(
+            order
+                .iter()
+                .filter_map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>(),
+            ["NemesisGift01", "NemesisGift02", "NemesisGift03"]
+        );
+    }
+
+    #[test]
+    fn set_level_text_lines() {
+        let (_, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut relationships = state.relationships_mut().unwrap();
+        relationships.set_level("NPC_Echo_01", 5).unwrap();
+        relationships.set_level("NPC_Nemesis_01", 1).unwrap();
+        relationships.set_level("ZeusUpgrade", 0).unwrap();
+
+        let game_state = lua_state.get("GameState").unwrap();
+        let played = |line: &str| {
+            game_state
+                .get("TextLinesRecord")
+                .and_then(|lines| lines.get(line))
+                .and_then(LuaValue::as_bool)
+                == Some(true)
+        };
+        let order = |name: &str| {
+            let order = game_state.get("GiftTextLinesOrderRecord")?.get(name)?;
+            let order = order.as_table()?.0.iter();
+            Some(
+                order
+                    .map(|(_, line)| line.as_str().unwrap())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert!(played("EchoGift04") && played("EchoGift05") && !played("EchoGift06"));
+        assert_eq!(
+            order("NPC_Echo_01").unwrap(),
+            [
+                "EchoGift01",
+                "EchoGift02",
+                "EchoGift03",
+                "EchoGift04",
+                "EchoGift05"
+            ]
+        );
+        assert!(played("NemesisGift01") && !played("NemesisGift02"));
+        assert_eq!(order("NPC_Nemesis_01").unwrap(), ["NemesisGift01"]);
+        assert!(!played("ZeusGift01"));
+        assert_eq!(order("ZeusUpgrade"), None);
+
+        let (_, mut lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut relationships = state.relationships_mut().unwrap();
+        relationships.set_level("NPC_Hecate_01", 4).unwrap();
+        relationships.max_out().unwrap();
+        assert!(
+            RELATIONSHIPS
+                .iter()
+                .filter(|info| info.gift_text_lines.is_some())
+                .all(|info| relationships.bond_forged(info.name))
+        );
+        assert_eq!(relationships.level("NPC_Eris_01"), 4);
+
+        let game_state = lua_state.get("GameState").unwrap();
+        let played = |line: &str| {
+            game_state
+                .get("TextLinesRecord")
+                .and_then(|lines| lines.get(line))
+                .and_then(LuaValue::as_bool)
+                == Some(true)
+        };
+        assert!(played("HecateGift05") && played("ZeusGift04") && played("CirceGift06"));
+        assert!(!played("HecateGift07") && played("ErisGift01_B"));
+        assert!(game_state.get("GiftTextLinesOrderRecord").is_none());
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, entries, insert};
+use crate::saves::LuaValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ResourceCategory {
+    /// Spent at the crossroads, like Bones or Psyche
+    Currency,
+    /// Given to characters and familiars, like Nectar
+    Gift,
+    /// Reagents dropped by the guardian of a region, like Cinder
+    BossDrop,
+    Ore,
+    Plant,
+    Seed,
+    Fish,
+}
+
+impl fmt::Display for ResourceCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResourceCategory::Currency => "Currencies",
+            ResourceCategory::Gift => "Gifts",
+            ResourceCategory::BossDrop => "Boss Drops",
+            ResourceCategory::Ore => "Ores",
+            ResourceCategory::Plant => "Plants",
+            ResourceCategory::Seed => "Seeds",
+            ResourceCategory::Fish => "Fish",
+        })
+    }
+}
+
+/// Entry of the [`RESOURCES`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceInfo {
+    /// Key in `GameState.Resources`
+    pub name: &'static str,
+    /// English name, which can differ a lot from the key, like `Ash` for `MetaCardPointsCommon`
+    pub display_name: &'static str,
+    pub category: ResourceCategory,
+}
+
+const fn resource(
+    name: &'static str,
+    display_name: &'static str,
+    category: ResourceCategory,
+) -> ResourceInfo {
+    ResourceInfo {
+        name,
+        display_name,
+        category,
+    }
+}
+
+/// The resources known to this crate, ordered by category.
+///
+/// Savefiles may contain resources which are not listed here, e.g. from newer game versions.
+#[rustfmt::skip]
+pub const RESOURCES: &[ResourceInfo] = {
+    use ResourceCategory::*;
+    &[
+        resource("Money", "Gold", Currency),
+        resource("MetaCurrency", "Bones", Currency),
+        resource("MetaCardPointsCommon", "Ash", Currency),
+        resource("MemPointsCommon", "Psyche", Currency),
+        resource("CardUpgradePoints", "Moon Dust", Currency),
+        resource("WeaponPointsRare", "Nightmare", Currency),
+        resource("MetaFabric", "Fate Fabric", Currency),
+        resource("CharonPoints", "Charon Cards", Currency),
+        resource("GiftPoints", "Nectar", Gift),
+        resource("GiftPointsRare", "Bath Salts", Gift),
+        resource("GiftPointsEpic", "Ambrosia", Gift),
+        resource("FamiliarPoints", "Witch's Delight", Gift),
+        resource("MixerFBoss", "Cinder", BossDrop),
+        resource("MixerGBoss", "Pearl", BossDrop),
+        resource("MixerHBoss", "Tears", BossDrop),
+        resource("MixerIBoss", "Zodiac Sand", BossDrop),
+        resource("OreFSilver", "Silver", Ore),
+        resource("OreGLime", "Limestone", Ore),
+        resource("OreHGlassrock", "Glassrock", Ore),
+        resource("OreIMarble", "Marble", Ore),
+        resource("OreNBronze", "Bronze", Ore),
+        resource("OreOIron", "Iron", Ore),
+        resource("PlantFMoly", "Moly", Plant),
+        resource("PlantFNightshade", "Nightshade", Plant),
+        resource("PlantGCattail", "Cattail", Plant),
+        resource("PlantGLotus", "Lotus", Plant),
+        resource("PlantHMyrtle", "Myrtle", Plant),
+        resource("PlantHWheat", "Wheat", Plant),
+        resource("PlantIPoppy", "Poppy", Plant),
+        resource("PlantIShaderot", "Shaderot", Plant),
+        resource("PlantNGarlic", "Garlic", Plant),
+        resource("PlantNMoss", "Moss", Plant),
+        resource("PlantODriftwood", "Driftwood", Plant),
+        resource("PlantOMandrake", "Mandrake", Plant),
+        resource("PlantChaosThalamus", "Thalamus", Plant),
+        resource("SeedMystery", "Mystery Seed", Seed),
+        resource("PlantFNightshadeSeed", "Nightshade Seed", Seed),
+        resource("PlantGCattailSeed", "Cattail Seed", Seed),
+        resource("PlantHWheatSeed", "Wheat Seed", Seed),
+        resource("PlantIPoppySeed", "Poppy Seed", Seed),
+        resource("PlantNGarlicSeed", "Garlic Seed", Seed),
+        resource("PlantOMandrakeSeed", "Mandrake Seed", Seed),
+        resource("PlantChaosThalamusSeed", "Thalamus Seed", Seed),
+        resource("FishFCommon", "Erebus Fish", Fish),
+        resource("FishFRare", "Rare Erebus Fish", Fish),
+        resource("FishFLegendary", "Legendary Erebus Fish", Fish),
+        resource("FishGCommon", "Oceanus Fish", Fish),
+        resource("FishGRare", "Rare Oceanus Fish", Fish),
+        resource("FishHCommon", "Mourning Fields Fish", Fish),
+        resource("FishNCommon", "Ephyra Fish", Fish),
+        resource("FishNRare", "Rare Ephyra Fish", Fish),
+        resource("FishNLegendary", "Legendary Ephyra Fish", Fish),
+        resource("FishOCommon", "Thessaly Fish", Fish),
+        resource("FishChaosCommon", "Chaos Fish", Fish),
+    ]
+};
+
+/// Looks up a resource in the [`RESOURCES`] catalogue by its key in `GameState.Resources`
+pub fn resource_info(name: &str) -> Option<&'static ResourceInfo> {
+    RESOURCES.iter().find(|info| info.name == name)
+}
+
+/// View over `GameState.Resources`, the currencies and materials the player owns.
+///
+/// Amounts are non-negative integers. Entries which aren't are reported as errors instead of being overwritten,
+/// and resources missing from the [`RESOURCES`] catalogue are read and written like any other.
+#[derive(Debug, Clone, Copy)]
+pub struct Resources<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Resources<V> {
+    pub fn value(&self) -> &LuaValue<'a> {
+        &self.value
+    }
+
+    /// The owned amount, or `None` if the resource was never obtained or is not an integer
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.value.get(name)?.as_u32()
+    }
+    /// All resources with an integer amount, in table order
+    pub fn iter<'v>(&'v self) -> impl Iterator<Item = (&'v str, u32)>
+    where
+        'a: 'v,
+    {
+        entries(&self.value).filter_map(|(key, val)| Some((key.as_str()?, val.as_u32()?)))
+    }
+
+    /// The owned amount, where a missing entry counts as 0
+    pub(super) fn amount(&self, name: &str) -> Result<u32> {
+        match self.value.get(name) {
+            None | Some(LuaValue::Nil) => Ok(0),
+            Some(val) => val.as_u32().ok_or_else(|| Error::InvalidAmount {
+                name: name.to_owned(),
+                value: format!("{val:?}"),
+            }),
+        }
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Resources<V> {
+    pub fn value_mut(&mut self) -> &mut LuaValue<'a> {
+        &mut self.value
+    }
+
+    /// Sets the owned amount, adding the resource if it is missing.
+    ///
+    /// Fails without changing anything if the current entry is not a non-negative integer.
+    pub fn set(&mut self, name: &str, amount: u32) -> Result<()> {
+        self.amount(name)?;
+        insert(&mut self.value, name, LuaValue::Number(amount.into()));
+        Ok(())
+    }
+
+    /// Adds `delta`, which may be negative, and returns the new amount.
+    ///
+    /// Fails without changing anything if the result would be negative or exceed [`u32::MAX`].
+    pub fn add(&mut self, name: &str, delta: i64) -> Result<u32> {
+        let current = self.amount(name)?;
+        let amount = i64::from(current)
+            .checked_add(delta)
+            .and_then(|amount| u32::try_from(amount).ok())
+            .ok_or_else(|| Error::AmountOutOfRange {
+                name: name.to_owned(),
+                current,
+                delta,
+            })?;
+        self.set(name, amount)?;
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RESOURCES, ResourceCategory, resource_info};
+    use crate::game::testdata::TEST_PROFILE_V18;
+    use crate::game::{Error, State};
+    use crate::saves::{Savefile, read_luatext};
+
+    #[test]
+    fn catalogue() {
+        assert!(RESOURCES.is_sorted_by_key(|info| info.category));
+        assert_eq!(
+            resource_info("MetaCardPointsCommon").unwrap().category,
+            ResourceCategory::Currency
+        );
+
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        let known = state
+            .resources()
+            .iter()
+            .filter(|(name, _)| resource_info(name).is_some())
+            .count();
+        assert_eq!(known, state.resources().iter().count() - 1); // PlantMoney
+    }
+
+    #[test]
+    fn set_add() {
+        let mut lua_state = read_luatext(
+            r#"{ GameState = { Resources = { Money = 10, Broken = "x", NewResource = 1 } } }"#,
+        )
+        .unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut resources = state.resources_mut().unwrap();
+
+        assert_eq!(resources.add("Money", -3).unwrap(), 7);
+        assert_eq!(resources.add("MetaCurrency", 5).unwrap(), 5);
+        assert!(matches!(
+            resources.add("Money", -8),
+            Err(Error::AmountOutOfRange { current: 7, .. })
+        ));
+        assert!(matches!(
+            resources.set("Broken", 1),
+            Err(Error::InvalidAmount { .. })
+        ));
+        resources.set("NewResource", 2).unwrap();
+
+        assert_eq!(
+            lua_state
+                .get("GameState")
+                .unwrap()
+                .get("Resources")
+                .unwrap(),
+            &read_luatext(r#"{ Broken = "x", MetaCurrency = 5, Money = 7, NewResource = 2 }"#)
+                .unwrap()
+        );
+        assert_eq!(
+            State::new(&lua_state).resources().get("Broken"),
+            None::<u32>
+        );
+    }
+}
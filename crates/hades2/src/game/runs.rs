@@ -0,0 +1,305 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{FAMILIARS, entries, weapon_info};
+use crate::saves::LuaValue;
+
+/// `RunResult` of runs ending in death, the only value seen so far
+const RUN_RESULT_DIED: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Cleared,
+    Died,
+    /// A `RunResult` this crate doesn't know about
+    Unknown(u32),
+}
+
+/// Summary of a finished run, see [`RunHistory::records`].
+///
+/// Names are the internal ones, like `WeaponAxe` or `AxeArmCastAspect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunRecord<'v> {
+    pub weapon: Option<&'v str>,
+    pub aspect: Option<&'v str>,
+    /// Like `FrogFamiliar`
+    pub familiar: Option<&'static str>,
+    pub keepsakes: Vec<&'v str>,
+    /// Olympian and other boons, named `*Boon`
+    pub boons: Vec<&'v str>,
+    /// Selene's hexes, named `Spell*Trait`
+    pub hexes: Vec<&'v str>,
+    /// All traits taken and how often, including boons, hexes, arcana cards and hammers
+    pub traits: Vec<(&'v str, u32)>,
+    /// Number of rooms entered
+    pub depth: Option<u32>,
+    pub outcome: RunOutcome,
+    /// Time spent in rooms, in seconds
+    pub gameplay_time: Option<f64>,
+    pub killed_by: Option<&'v str>,
+    pub ending_room: Option<&'v str>,
+    /// Ranks of the active vows
+    pub vows: Vec<(&'v str, u32)>,
+    /// Total fear of the active vows
+    pub fear: Option<u32>,
+}
+
+impl RunRecord<'_> {
+    /// The gameplay time, if the run was cleared
+    pub fn clear_time(&self) -> Option<f64> {
+        match self.outcome {
+            RunOutcome::Cleared => self.gameplay_time,
+            _ => None,
+        }
+    }
+}
+
+/// View over a single run, either `CurrentRun` or an entry of `GameState.RunHistory`
+#[derive(Debug, Clone, Copy)]
+pub struct Run<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Run<V> {
+    pub fn value(&self) -> &LuaValue<'a> {
+        &self.value
+    }
+
+    /// Number of rooms entered
+    pub fn depth(&self) -> Option<u32> {
+        if let Some(depth) = self.value.get("RunDepthCache") {
+            return depth.as_u32();
+        }
+        // most history entries of version 18 only count the rooms after the first
+        let rooms = self.value.get("RoomCountCache")?;
+        entries(rooms)
+            .map(|(_, count)| count.as_u32())
+            .sum::<Option<u32>>()
+            .map(|rooms| rooms + 1)
+    }
+    /// Time spent in rooms, in seconds
+    pub fn gameplay_time(&self) -> Option<f64> {
+        self.value.get("GameplayTime")?.as_number()
+    }
+    /// Name of the room the run ended in, like `H_Boss01`
+    pub fn ending_room<'v>(&'v self) -> Option<&'v str>
+    where
+        'a: 'v,
+    {
+        ending_room(&self.value)
+    }
+    /// Name of the enemy that killed the player, like `InfestedCerberus`
+    pub fn killed_by<'v>(&'v self) -> Option<&'v str>
+    where
+        'a: 'v,
+    {
+        killed_by(&self.value)
+    }
+}
+
+fn ending_room<'v>(value: &'v LuaValue<'_>) -> Option<&'v str> {
+    value.get("EndingRoomName")?.as_str()
+}
+fn killed_by<'v>(value: &'v LuaValue<'_>) -> Option<&'v str> {
+    value.get("KilledByName")?.as_str()
+}
+
+/// The outcome of a run in `RunHistory`.
+///
+/// Version 17 only marks cleared runs, so a run without any result there has died. `CurrentRun` looks the
+/// same while it is still going, which is why this only applies to finished runs.
+fn outcome(value: &LuaValue) -> RunOutcome {
+    let cleared = value.get("Cleared").and_then(LuaValue::as_bool);
+    let result = value.get("RunResult").and_then(LuaValue::as_u32);
+    match (cleared, result) {
+        (Some(true), _) => RunOutcome::Cleared,
+        (_, Some(RUN_RESULT_DIED) | None) => RunOutcome::Died,
+        (_, Some(result)) => RunOutcome::Unknown(result),
+    }
+}
+
+/// Extracts the typed summary of a finished run, leaving fields missing in this save version empty
+fn record<'v>(value: &'v LuaValue<'_>) -> RunRecord<'v> {
+    let run = Run { value };
+    let table_entries = |key| entries(value.get(key).unwrap_or(&LuaValue::Nil));
+
+    let traits: Vec<(&str, u32)> = table_entries("TraitCache")
+        .filter_map(|(name, count)| Some((name.as_str()?, count.as_u32()?)))
+        .collect();
+    let trait_names = || traits.iter().map(|&(name, _)| name);
+
+    let weapon = table_entries("WeaponsCache")
+        .filter_map(|(name, _)| name.as_str())
+        .find(|name| weapon_info(name).is_some());
+    // familiar traits are named like `FrogFamiliar` or `FamiliarFrogDamage`
+    let familiar = FAMILIARS.iter().find_map(|info| {
+        trait_names()
+            .any(|name| {
+                name == info.name
+                    || name
+                        .strip_prefix("Familiar")
+                        .is_some_and(|rest| rest.starts_with(info.prefix))
+            })
+            .then_some(info.name)
+    });
+
+    // version 18 lists the equipped keepsakes, version 17 only remembers the last one
+    let mut keepsakes: Vec<&str> = table_entries("KeepsakeCache")
+        .filter_map(|(_, name)| name.as_str())
+        .collect();
+    if keepsakes.is_empty() {
+        keepsakes.extend(trait_names().filter(|name| name.ends_with("Keepsake")));
+    }
+    if let Some(last) = value.get("EndingKeepsakeName").and_then(LuaValue::as_str)
+        && !keepsakes.contains(&last)
+    {
+        keepsakes.push(last);
+    }
+
+    RunRecord {
+        weapon,
+        aspect: trait_names().find(|name| name.ends_with("Aspect")),
+        familiar,
+        keepsakes,
+        boons: trait_names()
+            .filter(|name| name.ends_with("Boon"))
+            .collect(),
+        hexes: trait_names()
+            .filter(|name| name.starts_with("Spell") && name.ends_with("Trait"))
+            .collect(),
+        depth: run.depth(),
+        outcome: outcome(value),
+        gameplay_time: run.gameplay_time(),
+        killed_by: killed_by(value),
+        ending_room: ending_room(value),
+        vows: table_entries("ShrineUpgradesCache")
+            .filter_map(|(name, rank)| Some((name.as_str()?, rank.as_u32()?)))
+            .filter(|&(_, rank)| rank > 0)
+            .collect(),
+        fear: value.get("ShrinePointsCache").and_then(LuaValue::as_u32),
+        traits,
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Run<V> {
+    pub fn value_mut(&mut self) -> &mut LuaValue<'a> {
+        &mut self.value
+    }
+}
+
+/// View over `GameState.RunHistory`, the finished runs from oldest to newest.
+///
+/// Indices start at 0, while the lua array starts at 1.
+#[derive(Debug, Clone, Copy)]
+pub struct RunHistory<V> {
+    pub(super) value: V,
+}
+
+fn key(index: usize) -> LuaValue<'static> {
+    LuaValue::Number((index + 1) as f64)
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> RunHistory<V> {
+    pub fn value(&self) -> &LuaValue<'a> {
+        &self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.get(0).is_none()
+    }
+    pub fn get(&self, index: usize) -> Option<Run<&LuaValue<'a>>> {
+        let value = self.value.as_table()?.find(&key(index))?;
+        value.as_table().map(|_| Run { value })
+    }
+    pub fn iter<'v>(&'v self) -> impl Iterator<Item = Run<&'v LuaValue<'a>>>
+    where
+        'a: 'v,
+    {
+        (0..).map_while(move |index| self.get(index))
+    }
+    /// The [`RunRecord`]s of all finished runs, from oldest to newest
+    pub fn records<'v>(&'v self) -> impl Iterator<Item = RunRecord<'v>>
+    where
+        'a: 'v,
+    {
+        self.iter().map(|run| record(run.value))
+    }
+    /// The [`RunRecord`] of the run at `index`
+    pub fn record<'v>(&'v self, index: usize) -> Option<RunRecord<'v>>
+    where
+        'a: 'v,
+    {
+        self.get(index).map(|run| record(run.value))
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> RunHistory<V> {
+    pub fn value_mut(&mut self) -> &mut LuaValue<'a> {
+        &mut self.value
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<Run<&mut LuaValue<'a>>> {
+        self.get(index)?;
+        let value = self.value.as_table_mut()?.find_mut(&key(index))?;
+        value.as_table().is_some().then_some(Run { value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunOutcome;
+    use crate::game::State;
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::saves::Savefile;
+
+    #[test]
+    fn records_v17() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let state = State::new(&lua_state);
+        let history = state.run_history();
+        let records: Vec<_> = history.records().collect();
+        assert_eq!(records.len(), 80);
+        assert!(records.iter().all(|record| record.depth.is_some()));
+
+        let cleared = &records[78];
+        assert_eq!(cleared.outcome, RunOutcome::Cleared);
+        assert_eq!(cleared.weapon, Some("WeaponStaffSwing"));
+        assert_eq!(cleared.aspect, Some("StaffSelfHitAspect"));
+        assert_eq!(cleared.familiar, Some("FrogFamiliar"));
+        assert_eq!(
+            cleared.keepsakes,
+            ["ForceHephaestusBoonKeepsake", "TempHammerKeepsake"]
+        );
+        assert_eq!(cleared.hexes, ["SpellLeapTrait"]);
+        assert!(cleared.boons.contains(&"PoseidonWeaponBoon"));
+        assert_eq!(cleared.depth, Some(38));
+        assert_eq!(cleared.fear, Some(8));
+        assert_eq!(cleared.vows.len(), 5);
+        assert!(cleared.clear_time().is_some_and(|time| time > 1000.0));
+
+        assert_eq!(records[0].outcome, RunOutcome::Died);
+        assert_eq!(records[0].clear_time(), None);
+    }
+
+    #[test]
+    fn records_v18() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let state = State::new(&lua_state);
+        let history = state.run_history();
+        let records: Vec<_> = history.records().collect();
+        assert_eq!(records.len(), 9);
+
+        let first = &records[0];
+        assert_eq!(first.outcome, RunOutcome::Died);
+        assert_eq!(first.killed_by, Some("FishmanMelee"));
+        // derived from `RoomCountCache`, as `RunDepthCache` is missing
+        assert_eq!(first.depth, Some(23));
+        assert_eq!(records[8].depth, Some(42));
+        assert_eq!(records[3].keepsakes, ["ManaOverTimeRefundKeepsake"]);
+        assert_eq!(records[3].weapon, Some("WeaponDagger"));
+        assert_eq!(history.record(8).as_ref(), records.last());
+        assert_eq!(history.record(9), None);
+    }
+}
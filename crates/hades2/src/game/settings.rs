@@ -0,0 +1,90 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Result, Set, TableMut, insert, table};
+use crate::saves::{LuaValue, Savefile};
+
+/// View over the gameplay settings stored in `GameState`
+#[derive(Debug, Clone, Copy)]
+pub struct Settings<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Settings<V> {
+    /// `EasyModeLevel`, which grows with every death while God Mode is active
+    pub fn easy_mode_level(&self) -> Option<u32> {
+        self.value.get("EasyModeLevel")?.as_u32()
+    }
+    /// Whether God Mode is active, according to `Flags.EasyMode`.
+    ///
+    /// The header of the savefile repeats it as [`Savefile::easy_mode`].
+    pub fn god_mode(&self) -> bool {
+        self.flags().contains("EasyMode")
+    }
+    /// `Flags`, miscellaneous switches like `HasUpgradedCards`
+    pub fn flags(&self) -> Set<&LuaValue<'a>> {
+        Set {
+            value: table(&self.value, "Flags"),
+        }
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Settings<V> {
+    pub fn set_easy_mode_level(&mut self, level: u32) {
+        insert(
+            &mut self.value,
+            "EasyModeLevel",
+            LuaValue::Number(level.into()),
+        );
+    }
+    /// Switches God Mode on or off, in `Flags.EasyMode` and in [`Savefile::easy_mode`] of `savefile`,
+    /// which [`check_consistency`](super::check_consistency) compares.
+    ///
+    /// The game's scripts check `Flags.EasyMode` before scaling damage by the `EasyModeDamageMultiplier*`
+    /// values of `CurrentRun.Hero` and counting deaths in `EasyModeLevel`. Neither of those is changed,
+    /// so the damage resistance picks up where it was when God Mode is switched back on.
+    pub fn set_god_mode(&mut self, savefile: &mut Savefile, enabled: bool) -> Result<()> {
+        self.flags_mut()?.set("EasyMode", enabled);
+        savefile.easy_mode = enabled;
+        Ok(())
+    }
+    pub fn flags_mut(&mut self) -> Result<Set<TableMut<'_, V>>> {
+        Ok(Set {
+            value: TableMut::new(&mut self.value, &["Flags"])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::testdata::TEST_PROFILE_V18;
+    use crate::game::{HeaderField, State, check_consistency};
+    use crate::saves::Savefile;
+
+    #[test]
+    fn god_mode() {
+        // neither test save has God Mode on, the flag name comes from the game's scripts
+        let (mut savefile, mut lua_state) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut settings = state.settings_mut().unwrap();
+        assert!(!settings.god_mode());
+        assert_eq!(settings.easy_mode_level(), Some(0));
+
+        settings.set_god_mode(&mut savefile, true).unwrap();
+        assert!(settings.god_mode() && savefile.easy_mode);
+        settings.set_easy_mode_level(5);
+        assert_eq!(check_consistency(&savefile, &lua_state), []);
+
+        let mut state = State::new(&mut lua_state);
+        let mut settings = state.settings_mut().unwrap();
+        settings.set_god_mode(&mut savefile, false).unwrap();
+        assert!(!settings.god_mode() && !savefile.easy_mode);
+        assert_eq!(settings.easy_mode_level(), Some(5));
+        assert!(!settings.flags().iter().any(|flag| flag == "EasyMode"));
+        assert_eq!(check_consistency(&savefile, &lua_state), []);
+
+        savefile.easy_mode = true;
+        let inconsistencies = check_consistency(&savefile, &lua_state);
+        assert_eq!(inconsistencies.len(), 1);
+        assert_eq!(inconsistencies[0].field, HeaderField::EasyMode);
+    }
+}
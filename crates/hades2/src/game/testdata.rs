@@ -0,0 +1,52 @@
+//! Savefiles and checks shared by the tests of the views
+
+use std::fmt::Debug;
+
+use super::{
+    CARDS, FAMILIARS, INCANTATIONS, KEEPSAKES, QUESTS, RELATIONSHIPS, RESOURCES, VOWS, WEAPONS,
+    aspect_info, card_info, familiar_info, incantation_info, keepsake_info, quest_info,
+    relationship_info, resource_info, vow_info, weapon_info,
+};
+
+pub(super) const TEST_PROFILE_V17: &[u8] =
+    include_bytes!("../../../../testdata/Profile.v17.sav").as_slice();
+pub(super) const TEST_PROFILE_V18: &[u8] =
+    include_bytes!("../../../../testdata/Profile.v18.sav").as_slice();
+
+/// Asserts that the names of a catalogue are unique and that `lookup` finds every entry by its name
+fn check_catalogue<T: PartialEq + Debug>(
+    catalogue: &'static [T],
+    name: impl Fn(&T) -> &str,
+    lookup: impl Fn(&str) -> Option<&'static T>,
+) {
+    for (i, info) in catalogue.iter().enumerate() {
+        assert_eq!(lookup(name(info)), Some(info));
+        assert!(
+            !catalogue[..i].iter().any(|other| name(other) == name(info)),
+            "duplicate {info:?}"
+        );
+    }
+}
+
+#[test]
+fn catalogues() {
+    check_catalogue(CARDS, |info| info.name, card_info);
+    check_catalogue(FAMILIARS, |info| info.name, familiar_info);
+    check_catalogue(INCANTATIONS, |info| info.name, incantation_info);
+    check_catalogue(KEEPSAKES, |info| info.name, keepsake_info);
+    check_catalogue(QUESTS, |info| info.name, quest_info);
+    check_catalogue(RELATIONSHIPS, |info| info.name, relationship_info);
+    check_catalogue(RESOURCES, |info| info.name, resource_info);
+    check_catalogue(VOWS, |info| info.name, vow_info);
+    check_catalogue(WEAPONS, |info| info.name, weapon_info);
+    for weapon in WEAPONS {
+        check_catalogue(
+            weapon.aspects,
+            |aspect| aspect.name,
+            |name| aspect_info(name).map(|(_, aspect)| aspect),
+        );
+        for aspect in weapon.aspects {
+            assert_eq!(aspect_info(aspect.name).unwrap().0, weapon);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Result, Set, TableMut, table};
+use crate::saves::LuaValue;
+
+/// View over the unlock tables in `GameState`
+#[derive(Debug, Clone, Copy)]
+pub struct Unlocks<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Unlocks<V> {
+    /// `WeaponsUnlocked`, the weapons and gathering tools like `WeaponAxe` or `ToolPickaxe`
+    pub fn weapons(&self) -> Set<&LuaValue<'a>> {
+        Set {
+            value: table(&self.value, "WeaponsUnlocked"),
+        }
+    }
+    /// `WorldUpgrades`, the incantations and cosmetics added to the crossroads
+    pub fn world_upgrades(&self) -> Set<&LuaValue<'a>> {
+        Set {
+            value: table(&self.value, "WorldUpgrades"),
+        }
+    }
+    /// `FamiliarsUnlocked`
+    pub fn familiars(&self) -> Set<&LuaValue<'a>> {
+        Set {
+            value: table(&self.value, "FamiliarsUnlocked"),
+        }
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Unlocks<V> {
+    pub fn weapons_mut(&mut self) -> Result<Set<TableMut<'_, V>>> {
+        Ok(Set {
+            value: TableMut::new(&mut self.value, &["WeaponsUnlocked"])?,
+        })
+    }
+    pub fn world_upgrades_mut(&mut self) -> Result<Set<TableMut<'_, V>>> {
+        Ok(Set {
+            value: TableMut::new(&mut self.value, &["WorldUpgrades"])?,
+        })
+    }
+    pub fn familiars_mut(&mut self) -> Result<Set<TableMut<'_, V>>> {
+        Ok(Set {
+            value: TableMut::new(&mut self.value, &["FamiliarsUnlocked"])?,
+        })
+    }
+}
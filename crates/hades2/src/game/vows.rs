@@ -0,0 +1,255 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, TableMut, entries, insert, table};
+use crate::saves::{LuaValue, Savefile};
+
+/// Entry of the [`VOWS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VowInfo {
+    /// Key in `GameState.ShrineUpgrades`
+    pub name: &'static str,
+    /// Like `Vow of Pain`
+    pub display_name: &'static str,
+    /// Fear added by each rank, so its length is the highest rank
+    pub fear: &'static [u32],
+}
+
+impl VowInfo {
+    pub fn max_rank(&self) -> u32 {
+        self.fear.len() as u32
+    }
+    /// Total fear of the vow at `rank`
+    pub fn fear_at(&self, rank: u32) -> u32 {
+        self.fear.iter().take(rank as usize).sum()
+    }
+}
+
+const fn vow(name: &'static str, display_name: &'static str, fear: &'static [u32]) -> VowInfo {
+    VowInfo {
+        name,
+        display_name,
+        fear,
+    }
+}
+
+/// The vows of the Oath of the Unseen, in the order of the oath.
+///
+/// The fear of each rank is that of the game version the test saves come from,
+/// and a later patch may rebalance it.
+#[rustfmt::skip]
+pub const VOWS: &[VowInfo] = &[
+    vow("EnemyDamageShrineUpgrade", "Vow of Pain", &[1, 1, 1]),
+    vow("EnemyHealthShrineUpgrade", "Vow of Grit", &[1, 1, 1, 1, 1]),
+    vow("EnemyShieldShrineUpgrade", "Vow of Wards", &[1, 1]),
+    vow("EnemySpeedShrineUpgrade", "Vow of Frenzy", &[2, 4]),
+    vow("EnemyCountShrineUpgrade", "Vow of Hordes", &[1, 2, 3]),
+    vow("EnemyEliteShrineUpgrade", "Vow of Menace", &[2, 3]),
+    vow("EnemyRespawnShrineUpgrade", "Vow of Return", &[1, 2]),
+    vow("FirstDamageShrineUpgrade", "Vow of Fangs", &[1, 1, 1]),
+    vow("HealingReductionShrineUpgrade", "Vow of Scars", &[1, 1, 1, 1]),
+    vow("ShopPricesShrineUpgrade", "Vow of Debt", &[1, 1, 1]),
+    vow("RoomStartManaShrineUpgrade", "Vow of Shadow", &[1, 1, 1]),
+    vow("BanUnpickedBoonsShrineUpgrade", "Vow of Forfeit", &[3]),
+    vow("BiomeSpeedShrineUpgrade", "Vow of Time", &[1, 2, 3]),
+    vow("NoMetaUpgradesShrineUpgrade", "Vow of Void", &[2, 3]),
+    vow("BoonSkipShrineUpgrade", "Vow of Hubris", &[1, 1]),
+    vow("BoonManaReserveShrineUpgrade", "Vow of Denial", &[1, 1]),
+    vow("NextBiomeEnemyShrineUpgrade", "Vow of Rivals", &[1, 2]),
+];
+
+/// Looks up a vow in the [`VOWS`] catalogue by its key in `GameState.ShrineUpgrades`
+pub fn vow_info(name: &str) -> Option<&'static VowInfo> {
+    VOWS.iter().find(|info| info.name == name)
+}
+
+/// Rank of a single vow of the [`VOWS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VowState {
+    pub info: &'static VowInfo,
+    pub rank: u32,
+}
+
+impl VowState {
+    pub fn fear(&self) -> u32 {
+        self.info.fear_at(self.rank)
+    }
+}
+
+/// View over the Oath of the Unseen in `GameState.ShrineUpgrades`.
+///
+/// The header of the savefile repeats the total fear as [`Savefile::active_shrine_points`],
+/// which [`Vows::set_rank`] keeps in sync and [`Vows::validate`] checks.
+#[derive(Debug, Clone, Copy)]
+pub struct Vows<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Vows<V> {
+    pub fn rank(&self, name: &str) -> u32 {
+        table(&self.value, "ShrineUpgrades")
+            .get(name)
+            .and_then(LuaValue::as_u32)
+            .unwrap_or(0)
+    }
+    /// Every vow of the [`VOWS`] catalogue, in its order
+    pub fn iter(&self) -> impl Iterator<Item = VowState> + '_ {
+        VOWS.iter().map(|info| VowState {
+            info,
+            rank: self.rank(info.name),
+        })
+    }
+    /// Total fear of the active vows, where ranks above a vow's maximum count as the maximum.
+    ///
+    /// Only vows of the catalogue are counted, see [`Vows::unknown`] for the others.
+    pub fn fear(&self) -> u32 {
+        self.iter().map(|vow| vow.fear()).sum()
+    }
+    /// Active vows which are missing from the [`VOWS`] catalogue, with their rank
+    pub fn unknown<'v>(&'v self) -> impl Iterator<Item = (&'v str, u32)>
+    where
+        'a: 'v,
+    {
+        entries(table(&self.value, "ShrineUpgrades"))
+            .filter_map(|(name, rank)| Some((name.as_str()?, rank.as_u32()?)))
+            .filter(|&(name, rank)| rank > 0 && vow_info(name).is_none())
+    }
+
+    /// Checks that the header's `active_shrine_points` match the fear of the active vows.
+    ///
+    /// An active vow missing from the catalogue is reported as [`Error::UnknownVow`],
+    /// since its fear can't be known.
+    pub fn validate(&self, savefile: &Savefile) -> Result<()> {
+        if let Some((name, _)) = self.unknown().next() {
+            return Err(Error::UnknownVow(name.to_owned()));
+        }
+        let fear = self.fear();
+        match savefile.active_shrine_points == fear {
+            true => Ok(()),
+            false => Err(Error::FearMismatch {
+                header: savefile.active_shrine_points,
+                vows: fear,
+            }),
+        }
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Vows<V> {
+    /// Sets the rank of a vow, where 0 disables it.
+    ///
+    /// Updates the fear in `SpentShrinePointsCache` and the header of `savefile` as well.
+    pub fn set_rank(&mut self, savefile: &mut Savefile, name: &str, rank: u32) -> Result<()> {
+        let info = vow_info(name).ok_or_else(|| Error::UnknownVow(name.to_owned()))?;
+        if rank > info.max_rank() {
+            return Err(Error::InvalidVowRank {
+                name: name.to_owned(),
+                rank,
+                max_rank: info.max_rank(),
+            });
+        }
+        let mut vows = TableMut::new(&mut self.value, &["ShrineUpgrades"])?;
+        insert(&mut vows, name, LuaValue::Number(rank.into()));
+        let fear = self.fear();
+        insert(
+            &mut self.value,
+            "SpentShrinePointsCache",
+            LuaValue::Number(fear.into()),
+        );
+        savefile.active_shrine_points = fear;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VOWS, vow_info};
+    use crate::game::testdata::{TEST_PROFILE_V17, TEST_PROFILE_V18};
+    use crate::game::{Error, State};
+    use crate::saves::{Savefile, read_luatext};
+
+    #[test]
+    fn fear() {
+        for (data, fear) in [(TEST_PROFILE_V17, 7), (TEST_PROFILE_V18, 0)] {
+            let (savefile, lua_state) = Savefile::parse(data).unwrap();
+            let state = State::new(&lua_state);
+            assert_eq!(state.vows().fear(), fear);
+            state.vows().validate(&savefile).unwrap();
+        }
+
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let state = State::new(&lua_state);
+        let active: Vec<_> = state
+            .vows()
+            .iter()
+            .filter(|vow| vow.rank > 0)
+            .map(|vow| (vow.info.name, vow.rank))
+            .collect();
+        assert_eq!(
+            active,
+            [
+                ("EnemySpeedShrineUpgrade", 2),
+                ("ShopPricesShrineUpgrade", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_vows() {
+        let (mut savefile, _) = Savefile::parse(TEST_PROFILE_V18).unwrap();
+        let lua_state = read_luatext(
+            r#"{ GameState = { ShrineUpgrades = {
+                ShopPricesShrineUpgrade = 1, NewShrineUpgrade = 2, OldShrineUpgrade = 0,
+            } } }"#,
+        )
+        .unwrap();
+        let state = State::new(&lua_state);
+        let vows = state.vows();
+        assert_eq!(vows.fear(), 1);
+        assert_eq!(
+            vows.unknown().collect::<Vec<_>>(),
+            [("NewShrineUpgrade", 2)]
+        );
+
+        savefile.active_shrine_points = 3;
+        assert!(matches!(
+            vows.validate(&savefile),
+            Err(Error::UnknownVow(name)) if name == "NewShrineUpgrade"
+        ));
+    }
+
+    #[test]
+    fn set_rank() {
+        let (mut savefile, mut lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut vows = state.vows_mut().unwrap();
+
+        vows.set_rank(&mut savefile, "EnemySpeedShrineUpgrade", 0)
+            .unwrap();
+        vows.set_rank(&mut savefile, "BanUnpickedBoonsShrineUpgrade", 1)
+            .unwrap();
+        assert_eq!(savefile.active_shrine_points, 4);
+        vows.validate(&savefile).unwrap();
+
+        assert!(matches!(
+            vows.set_rank(&mut savefile, "BanUnpickedBoonsShrineUpgrade", 2),
+            Err(Error::InvalidVowRank { max_rank: 1, .. })
+        ));
+        assert!(matches!(
+            vows.set_rank(&mut savefile, "Unknown", 1),
+            Err(Error::UnknownVow(_))
+        ));
+
+        savefile.active_shrine_points = 9;
+        assert!(matches!(
+            vows.validate(&savefile),
+            Err(Error::FearMismatch { header: 9, vows: 4 })
+        ));
+
+        let max_fear: u32 = VOWS.iter().map(|info| info.fear_at(info.max_rank())).sum();
+        for info in VOWS {
+            vows.set_rank(&mut savefile, info.name, info.max_rank())
+                .unwrap();
+        }
+        assert_eq!(savefile.active_shrine_points, max_fear);
+        assert_eq!(vow_info("ShopPricesShrineUpgrade").unwrap().fear_at(2), 2);
+    }
+}
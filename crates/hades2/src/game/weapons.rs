@@ -0,0 +1,298 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{Error, Result, Set, TableMut, check_table, table};
+use crate::saves::LuaValue;
+
+/// Highest rank an aspect can be upgraded to
+pub const MAX_ASPECT_RANK: u32 = 5;
+
+/// Entry of the [`WEAPONS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeaponInfo {
+    /// Key in `GameState.WeaponsUnlocked`
+    pub name: &'static str,
+    /// Name of the Nocturnal Arm, like `Witch's Staff`
+    pub display_name: &'static str,
+    /// The aspects of the weapon, starting with the one it is unlocked with
+    pub aspects: &'static [AspectInfo],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectInfo {
+    /// Key in `GameState.WeaponsUnlocked` for rank 1, higher ranks append the rank like `AxeArmCastAspect2`
+    pub name: &'static str,
+    /// Like `Aspect of Momus`
+    pub display_name: &'static str,
+}
+
+const fn weapon(
+    name: &'static str,
+    display_name: &'static str,
+    aspects: &'static [AspectInfo],
+) -> WeaponInfo {
+    WeaponInfo {
+        name,
+        display_name,
+        aspects,
+    }
+}
+
+const fn aspect(name: &'static str, display_name: &'static str) -> AspectInfo {
+    AspectInfo { name, display_name }
+}
+
+/// The Nocturnal Arms, in the order of the training grounds
+#[rustfmt::skip]
+pub const WEAPONS: &[WeaponInfo] = &[
+    weapon("WeaponStaffSwing", "Witch's Staff", &[
+        aspect("BaseStaffAspect", "Aspect of Melinoë"),
+        aspect("StaffClearCastAspect", "Aspect of Circe"),
+        aspect("StaffSelfHitAspect", "Aspect of Momus"),
+        aspect("StaffRaiseDeadAspect", "Aspect of Anubis"),
+    ]),
+    weapon("WeaponDagger", "Sister Blades", &[
+        aspect("DaggerBackstabAspect", "Aspect of Melinoë"),
+        aspect("DaggerBlockAspect", "Aspect of Pan"),
+        aspect("DaggerHomingThrowAspect", "Aspect of Artemis"),
+        aspect("DaggerTripleAspect", "Aspect of the Morrigan"),
+    ]),
+    weapon("WeaponTorch", "Umbral Flames", &[
+        aspect("TorchSpecialDurationAspect", "Aspect of Melinoë"),
+        aspect("TorchDetonateAspect", "Aspect of Moros"),
+        aspect("TorchSprintRecallAspect", "Aspect of Eos"),
+        aspect("TorchAutofireAspect", "Aspect of Supay"),
+    ]),
+    weapon("WeaponAxe", "Moonstone Axe", &[
+        aspect("AxeRecoveryAspect", "Aspect of Melinoë"),
+        aspect("AxeArmCastAspect", "Aspect of Charon"),
+        aspect("AxePerfectCriticalAspect", "Aspect of Thanatos"),
+        aspect("AxeRallyAspect", "Aspect of Nergal"),
+    ]),
+    weapon("WeaponLob", "Argent Skull", &[
+        aspect("LobAmmoBoostAspect", "Aspect of Melinoë"),
+        aspect("LobCloseAttackAspect", "Aspect of Medea"),
+        aspect("LobImpulseAspect", "Aspect of Persephone"),
+        aspect("LobGunAspect", "Aspect of Hel"),
+    ]),
+    weapon("WeaponSuit", "Black Coat", &[
+        aspect("BaseSuitAspect", "Aspect of Melinoë"),
+        aspect("SuitHexAspect", "Aspect of Selene"),
+        aspect("SuitMarkCritAspect", "Aspect of Nyx"),
+        aspect("SuitComboAspect", "Aspect of Shiva"),
+    ]),
+];
+
+/// Looks up a weapon in the [`WEAPONS`] catalogue by its key in `GameState.WeaponsUnlocked`
+pub fn weapon_info(name: &str) -> Option<&'static WeaponInfo> {
+    WEAPONS.iter().find(|info| info.name == name)
+}
+
+/// Looks up an aspect and its weapon in the [`WEAPONS`] catalogue by the aspect's rank 1 name
+pub fn aspect_info(name: &str) -> Option<(&'static WeaponInfo, &'static AspectInfo)> {
+    WEAPONS.iter().find_map(|weapon| {
+        let aspect = weapon.aspects.iter().find(|aspect| aspect.name == name)?;
+        Some((weapon, aspect))
+    })
+}
+
+/// The unlock entry of an aspect at `rank`, starting at 1
+fn rank_name(aspect: &AspectInfo, rank: u32) -> String {
+    match rank {
+        1 => aspect.name.to_owned(),
+        _ => format!("{}{rank}", aspect.name),
+    }
+}
+
+/// State of a single aspect of the [`WEAPONS`] catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AspectState {
+    pub weapon: &'static WeaponInfo,
+    pub info: &'static AspectInfo,
+    /// 0 while the aspect is locked
+    pub rank: u32,
+}
+
+/// View over the Nocturnal Arms and their aspects in `GameState`.
+///
+/// Weapons and every rank of an aspect are recorded in `WeaponsUnlocked`, and everything bought
+/// at the training grounds is also recorded in `WorldUpgrades` and `WorldUpgradesAdded`.
+/// The first aspect of every weapon comes with it, so its first rank is only in `WeaponsUnlocked`.
+#[derive(Debug, Clone, Copy)]
+pub struct Weapons<V> {
+    pub(super) value: V,
+}
+
+impl<'a, V: Deref<Target = LuaValue<'a>>> Weapons<V> {
+    fn unlocked(&self) -> Set<&LuaValue<'a>> {
+        Set {
+            value: table(&self.value, "WeaponsUnlocked"),
+        }
+    }
+
+    pub fn is_unlocked(&self, weapon: &str) -> bool {
+        self.unlocked().contains(weapon)
+    }
+    /// The unlocked weapons of the [`WEAPONS`] catalogue
+    pub fn iter(&self) -> impl Iterator<Item = &'static WeaponInfo> + '_ {
+        WEAPONS.iter().filter(|info| self.is_unlocked(info.name))
+    }
+
+    /// The highest unlocked rank of an aspect, or 0 if it is locked
+    pub fn aspect_rank(&self, aspect: &str) -> u32 {
+        let Some((_, info)) = aspect_info(aspect) else {
+            return 0;
+        };
+        let unlocked = self.unlocked();
+        (1..=MAX_ASPECT_RANK)
+            .rev()
+            .find(|&rank| unlocked.contains(&rank_name(info, rank)))
+            .unwrap_or(0)
+    }
+    /// Every aspect of the [`WEAPONS`] catalogue, including locked ones
+    pub fn aspects(&self) -> impl Iterator<Item = AspectState> + '_ {
+        WEAPONS.iter().flat_map(move |weapon| {
+            weapon.aspects.iter().map(move |info| AspectState {
+                weapon,
+                info,
+                rank: self.aspect_rank(info.name),
+            })
+        })
+    }
+}
+
+impl<'a, V: DerefMut<Target = LuaValue<'a>>> Weapons<V> {
+    /// Fails if a table [`Weapons::set_entry`] writes to is not a table, before anything is written
+    fn check_tables(&self) -> Result<()> {
+        for key in ["WeaponsUnlocked", "WorldUpgrades", "WorldUpgradesAdded"] {
+            check_table(&self.value, key)?;
+        }
+        Ok(())
+    }
+    fn set_entry(&mut self, name: &str, bought: bool, unlocked: bool) -> Result<()> {
+        let keys: &[&'static str] = match bought {
+            true => &["WeaponsUnlocked", "WorldUpgrades", "WorldUpgradesAdded"],
+            false => &["WeaponsUnlocked"],
+        };
+        for &key in keys {
+            Set {
+                value: TableMut::new(&mut self.value, &[key])?,
+            }
+            .set(name, unlocked);
+        }
+        Ok(())
+    }
+
+    /// Unlocks or locks a weapon, which unlocks its first aspect or locks all of them
+    pub fn set_unlocked(&mut self, weapon: &str, unlocked: bool) -> Result<()> {
+        let info = weapon_info(weapon).ok_or_else(|| Error::UnknownWeapon(weapon.to_owned()))?;
+        self.check_tables()?;
+        self.set_entry(info.name, true, unlocked)?;
+        match unlocked {
+            true if self.aspect_rank(info.aspects[0].name) == 0 => {
+                self.set_aspect_rank(info.aspects[0].name, 1)
+            }
+            true => Ok(()),
+            false => info
+                .aspects
+                .iter()
+                .try_for_each(|aspect| self.set_aspect_rank(aspect.name, 0)),
+        }
+    }
+
+    /// Sets the rank of an aspect, where 0 locks it.
+    ///
+    /// The aspect's weapon needs to be unlocked, unless the aspect is being locked.
+    pub fn set_aspect_rank(&mut self, aspect: &str, rank: u32) -> Result<()> {
+        let (weapon, info) =
+            aspect_info(aspect).ok_or_else(|| Error::UnknownAspect(aspect.to_owned()))?;
+        if rank > MAX_ASPECT_RANK {
+            return Err(Error::InvalidAspectRank {
+                name: aspect.to_owned(),
+                rank,
+            });
+        }
+        if rank > 0 && !self.is_unlocked(weapon.name) {
+            return Err(Error::WeaponLocked(weapon.name.to_owned()));
+        }
+        self.check_tables()?;
+
+        let default = info == &weapon.aspects[0];
+        for current in 1..=MAX_ASPECT_RANK {
+            let bought = !(default && current == 1);
+            self.set_entry(&rank_name(info, current), bought, current <= rank)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_ASPECT_RANK;
+    use crate::game::testdata::TEST_PROFILE_V17;
+    use crate::game::{Error, State};
+    use crate::saves::{Savefile, read_luatext};
+
+    #[test]
+    fn read_weapons() {
+        let (_, lua_state) = Savefile::parse(TEST_PROFILE_V17).unwrap();
+        let state = State::new(&lua_state);
+        let weapons = state.weapons();
+        assert_eq!(weapons.iter().count(), 5);
+        assert!(!weapons.is_unlocked("WeaponSuit"));
+        assert_eq!(weapons.aspect_rank("AxeArmCastAspect"), MAX_ASPECT_RANK);
+        assert_eq!(weapons.aspect_rank("StaffSelfHitAspect"), 2);
+        assert_eq!(weapons.aspect_rank("StaffClearCastAspect"), 0);
+        assert_eq!(
+            weapons.aspects().filter(|aspect| aspect.rank > 0).count(),
+            14
+        );
+    }
+
+    #[test]
+    fn set_ranks() {
+        let mut lua_state = read_luatext(
+            r#"{ GameState = {
+                WeaponsUnlocked = { WeaponAxe = true, AxeArmCastAspect = true, AxeArmCastAspect2 = true },
+                WorldUpgrades = { WeaponAxe = true, AxeArmCastAspect = true, AxeArmCastAspect2 = true },
+            } }"#,
+        )
+        .unwrap();
+        let mut state = State::new(&mut lua_state);
+        let mut weapons = state.weapons_mut().unwrap();
+
+        assert!(matches!(
+            weapons.set_aspect_rank("LobGunAspect", 1),
+            Err(Error::WeaponLocked(_))
+        ));
+        assert!(matches!(
+            weapons.set_aspect_rank("AxeArmCastAspect", 6),
+            Err(Error::InvalidAspectRank { rank: 6, .. })
+        ));
+        assert!(matches!(
+            weapons.set_unlocked("WeaponBow", true),
+            Err(Error::UnknownWeapon(_))
+        ));
+        weapons.set_aspect_rank("AxeArmCastAspect", 1).unwrap();
+        weapons.set_unlocked("WeaponLob", true).unwrap();
+        weapons.set_aspect_rank("LobAmmoBoostAspect", 2).unwrap();
+        assert_eq!(weapons.aspect_rank("LobAmmoBoostAspect"), 2);
+
+        let expected = read_luatext(
+            r#"{ GameState = {
+                WeaponsUnlocked = {
+                    WeaponAxe = true, AxeArmCastAspect = true,
+                    WeaponLob = true, LobAmmoBoostAspect = true, LobAmmoBoostAspect2 = true,
+                },
+                WorldUpgrades = { WeaponAxe = true, AxeArmCastAspect = true, WeaponLob = true, LobAmmoBoostAspect2 = true },
+                WorldUpgradesAdded = { AxeArmCastAspect = true, WeaponLob = true, LobAmmoBoostAspect2 = true },
+            } }"#,
+        )
+        .unwrap();
+        assert!(lua_state.canonical_eq(&expected));
+
+        let mut state = State::new(&mut lua_state);
+        let mut weapons = state.weapons_mut().unwrap();
+        weapons.set_unlocked("WeaponLob", false).unwrap();
+        assert_eq!(weapons.aspect_rank("LobAmmoBoostAspect"), 0);
+    }
+}
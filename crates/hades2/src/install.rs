@@ -0,0 +1,256 @@
+//! Locating the game installation and reading savefiles from disk.
+
+use std::path::{Path, PathBuf};
+
+use crate::saves::{self, LuaValue};
+use crate::{Error, Result};
+
+#[allow(unused)]
+#[derive(Debug, thiserror::Error)]
+pub enum LocateError {
+    #[error("platform not supported")]
+    UnsupportedPlatform,
+    #[error("{0} was not found")]
+    NotFound(&'static str),
+    #[error("error trying to find directory")]
+    Other,
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub(crate) fn save_dir(_steam_dir: &Path) -> Result<PathBuf, LocateError> {
+    Err(LocateError::UnsupportedPlatform)
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn save_dir(_steam_dir: &Path) -> Result<PathBuf, LocateError> {
+    let home_dir = std::env::home_dir().ok_or_else(|| LocateError::Other)?;
+    let dir = home_dir.join("Saved Games/Hades II");
+    if !dir.exists() {
+        return Err(LocateError::NotFound("`~/Saved Games/Hades II`"));
+    }
+    Ok(dir)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn save_dir(_steam_dir: &Path) -> Result<PathBuf, LocateError> {
+    Err(LocateError::NotFound(
+        "macOS not supported yet. Please open an issue on github. save dir",
+    ))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn save_dir(steam_dir: &Path) -> Result<PathBuf, LocateError> {
+    let dir = steam_dir
+        .join("steamapps/compatdata/1145350/pfx/drive_c/users/steamuser/Saved Games/Hades II");
+    if !dir.exists() {
+        return Err(LocateError::NotFound("steam game 1145350"));
+    }
+
+    Ok(dir)
+}
+
+#[derive(Debug, Clone)]
+pub struct Hades2Installation {
+    #[allow(unused)]
+    steam_dir: PathBuf,
+    save_dir: PathBuf,
+}
+impl Hades2Installation {
+    pub fn steam_dir(&self) -> &Path {
+        &self.steam_dir
+    }
+    pub fn save_dir(&self) -> &Path {
+        &self.save_dir
+    }
+
+    pub fn detect() -> Result<Self> {
+        let steam_dir = crate::steamlocate::locate_steam_dir()?;
+        let save_dir = save_dir(&steam_dir)?;
+
+        Ok(Hades2Installation {
+            steam_dir,
+            save_dir,
+        })
+    }
+
+    pub fn active_profile_path(&self) -> Result<String> {
+        let path = self.save_dir().join("activeProfile");
+        let active_profile = std::fs::read(&path).map_err(Error::io(&path))?;
+        let active_profile = saves::parse_active_profile(&mut active_profile.as_slice())?;
+
+        Ok(active_profile.to_owned())
+    }
+
+    pub fn save(&self, slot: u32) -> Result<SaveHandle> {
+        let path = self.save_dir.join(format!("Profile{slot}.sav"));
+        if !path.exists() {
+            return Err(Error::SaveNotFound(slot));
+        }
+        SaveHandle::from_path(path).ok_or(Error::SaveNotFound(slot))
+    }
+    pub fn backups(&self, slot: u32) -> Result<Vec<SaveHandle>> {
+        let mut saves =
+            self.saves_inner(|handle| handle.slot == slot && handle.backup_index.is_some())?;
+        saves.sort_by_key(|handle| std::cmp::Reverse(handle.backup_index));
+        Ok(saves)
+    }
+
+    pub fn saves(&self) -> Result<Vec<SaveHandle>> {
+        let mut saves = self.saves_inner(|handle| handle.backup_index().is_none())?;
+        saves.sort_by_key(|handle| (handle.slot, handle.backup_index));
+        Ok(saves)
+    }
+
+    fn saves_inner(&self, f: impl Fn(&SaveHandle) -> bool) -> Result<Vec<SaveHandle>> {
+        let mut saves = Vec::new();
+        let entries = self
+            .save_dir
+            .read_dir()
+            .map_err(Error::io(&self.save_dir))?;
+        for save in entries {
+            let save = save.map_err(Error::io(&self.save_dir))?;
+            if let Some(handle) = SaveHandle::from_path(save.path())
+                && f(&handle)
+            {
+                saves.push(handle);
+            }
+        }
+
+        Ok(saves)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveHandle {
+    path: PathBuf,
+    slot: u32,
+    backup_index: Option<u32>,
+}
+impl SaveHandle {
+    pub fn from_path(path: PathBuf) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        let ext = path.extension()?.to_str()?;
+
+        let bak = ext.strip_prefix("bak").and_then(|x| x.parse::<u32>().ok());
+
+        let stem = match bak {
+            Some(_) => stem.strip_suffix(".sav")?,
+            None if ext != "sav" => return None,
+            None => stem,
+        };
+
+        let slot = stem.strip_prefix("Profile")?.parse().ok()?;
+
+        Some(SaveHandle {
+            path,
+            slot,
+            backup_index: bak,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+    pub fn backup_index(&self) -> Option<u32> {
+        self.backup_index
+    }
+
+    pub fn read(&self) -> Result<(saves::Savefile, LuaValue<'static>)> {
+        let data = std::fs::read(&self.path).map_err(Error::io(&self.path))?;
+        let result = saves::Savefile::parse(&data)?;
+        Ok(result)
+    }
+
+    pub fn read_header_only(&self) -> Result<saves::Savefile> {
+        let data = std::fs::read(&self.path).map_err(Error::io(&self.path))?;
+        let savefile = saves::Savefile::parse_header_only(&data)?;
+        Ok(savefile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SaveHandle;
+    use crate::{Error, ParseError};
+
+    #[test]
+    fn error_kinds() {
+        let path = std::env::temp_dir().join("hades2-missing/Profile1.sav");
+        let handle = SaveHandle::from_path(path.clone()).unwrap();
+        match handle.read() {
+            Err(Error::Io {
+                path: error_path,
+                source,
+            }) => {
+                assert_eq!(error_path, path);
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            }
+            other => panic!("{other:?}"),
+        }
+
+        let mut data = include_bytes!("../../../testdata/Profile.v18.sav").to_vec();
+        // stored checksum
+        data[4] ^= 1;
+        assert!(matches!(
+            crate::saves::Savefile::parse(&data).map_err(Error::from),
+            Err(Error::Parse(ParseError::Checksum))
+        ));
+    }
+
+    #[test]
+    fn save_handle_ok() {
+        let path = PathBuf::from("Profile1.sav");
+        assert_eq!(
+            SaveHandle::from_path(path.clone()).unwrap(),
+            SaveHandle {
+                path,
+                slot: 1,
+                backup_index: None
+            }
+        );
+
+        let path = PathBuf::from("Profile10.sav");
+        assert_eq!(
+            SaveHandle::from_path(path.clone()).unwrap(),
+            SaveHandle {
+                path,
+                slot: 10,
+                backup_index: None
+            }
+        );
+    }
+
+    #[test]
+    fn save_handle_invalid_ext() {
+        let path = PathBuf::from("Profile1.sjson");
+        assert_eq!(SaveHandle::from_path(path.clone()), None);
+    }
+
+    #[test]
+    fn save_handle_bak() {
+        let path = PathBuf::from("Profile1.sav.bak1");
+        assert_eq!(
+            SaveHandle::from_path(path.clone()).unwrap(),
+            SaveHandle {
+                path,
+                slot: 1,
+                backup_index: Some(1)
+            }
+        );
+
+        let path = PathBuf::from("Profile2.sav.bak10");
+        assert_eq!(
+            SaveHandle::from_path(path.clone()).unwrap(),
+            SaveHandle {
+                path,
+                slot: 2,
+                backup_index: Some(10)
+            }
+        );
+    }
+}
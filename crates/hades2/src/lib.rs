@@ -1,194 +1,63 @@
+//! Reading and writing Hades II savefiles.
+//!
+//! The format code does no filesystem IO, so it can be used from wasm or servers.
+//! Locating the game installation and reading saves from disk (`Hades2Installation`, `SaveHandle`)
+//! requires the `install` feature.
+
+#[cfg(feature = "install")]
+mod install;
 mod parser;
+#[cfg(feature = "install")]
 mod steamlocate;
 
+pub mod analysis;
+pub mod diff;
+pub mod game;
 pub mod saves;
 
-use anyhow::Context;
-pub use anyhow::{Error, Result};
-use saves::LuaValue;
+#[cfg(feature = "install")]
+pub use install::{Hades2Installation, LocateError, SaveHandle};
+pub use parser::Error as ParseError;
+#[cfg(feature = "serde")]
+pub use parser::de::{Error as SerdeError, from_lua};
+#[cfg(feature = "serde")]
+pub use parser::ser::to_lua;
+#[cfg(feature = "install")]
 use std::path::{Path, PathBuf};
 
-#[allow(unused)]
-#[derive(Debug, thiserror::Error)]
-pub(crate) enum LocateError {
-    #[error("platform not supported")]
-    UnsupportedPlatform,
-    #[error("{0} was not found")]
-    NotFound(&'static str),
-    #[error("error trying to find directory")]
-    Other,
-}
+pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(Debug, Clone)]
-pub struct Hades2Installation {
-    #[allow(unused)]
-    steam_dir: PathBuf,
-    save_dir: PathBuf,
+/// Error of everything that reads savefiles or locates the game installation
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reading a file or directory failed, e.g. because it doesn't exist
+    #[cfg(feature = "install")]
+    #[error("failed to access `{}`", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The savefile is invalid, e.g. its checksum doesn't match
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// The lua state doesn't have the expected shape
+    #[error(transparent)]
+    Game(#[from] game::Error),
+    /// Steam or the save directory could not be found
+    #[cfg(feature = "install")]
+    #[error(transparent)]
+    Locate(#[from] LocateError),
+    #[cfg(feature = "install")]
+    #[error("save {0} does not exist")]
+    SaveNotFound(u32),
 }
-impl Hades2Installation {
-    pub fn steam_dir(&self) -> &Path {
-        &self.steam_dir
-    }
-    pub fn save_dir(&self) -> &Path {
-        &self.save_dir
-    }
-
-    pub fn detect() -> Result<Self> {
-        let steam_dir = crate::steamlocate::locate_steam_dir()?;
-        let save_dir = saves::save_dir(&steam_dir)?;
-
-        Ok(Hades2Installation {
-            steam_dir,
-            save_dir,
-        })
-    }
-
-    pub fn active_profile_path(&self) -> Result<String> {
-        let active_profile = std::fs::read(self.save_dir().join("activeProfile"))?;
-        let active_profile = saves::parse_active_profile(&mut active_profile.as_slice())?;
-
-        Ok(active_profile.to_owned())
-    }
 
-    pub fn save(&self, slot: u32) -> Result<SaveHandle> {
-        let path = self.save_dir.join(format!("Profile{slot}.sav"));
-        anyhow::ensure!(path.exists(), "save {slot} does not exist");
-        let handle = SaveHandle::from_path(path).context("context")?;
-        Ok(handle)
-    }
-    pub fn backups(&self, slot: u32) -> Result<Vec<SaveHandle>> {
-        let mut saves =
-            self.saves_inner(|handle| handle.slot == slot && handle.backup_index.is_some())?;
-        saves.sort_by_key(|handle| std::cmp::Reverse(handle.backup_index));
-        Ok(saves)
-    }
-
-    pub fn saves(&self) -> Result<Vec<SaveHandle>> {
-        let mut saves = self.saves_inner(|handle| handle.backup_index().is_none())?;
-        saves.sort_by_key(|handle| (handle.slot, handle.backup_index));
-        Ok(saves)
-    }
-
-    fn saves_inner(&self, f: impl Fn(&SaveHandle) -> bool) -> Result<Vec<SaveHandle>> {
-        let mut saves = Vec::new();
-        for save in self.save_dir.read_dir()? {
-            if let Some(handle) = SaveHandle::from_path(save?.path())
-                && f(&handle)
-            {
-                saves.push(handle);
-            }
+#[cfg(feature = "install")]
+impl Error {
+    pub(crate) fn io(path: &Path) -> impl FnOnce(std::io::Error) -> Error {
+        move |source| Error::Io {
+            path: path.to_owned(),
+            source,
         }
-
-        Ok(saves)
-    }
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct SaveHandle {
-    path: PathBuf,
-    slot: u32,
-    backup_index: Option<u32>,
-}
-impl SaveHandle {
-    pub fn from_path(path: PathBuf) -> Option<Self> {
-        let stem = path.file_stem()?.to_str()?;
-        let ext = path.extension()?.to_str()?;
-
-        let bak = ext.strip_prefix("bak").and_then(|x| x.parse::<u32>().ok());
-
-        let stem = match bak {
-            Some(_) => stem.strip_suffix(".sav")?,
-            None if ext != "sav" => return None,
-            None => stem,
-        };
-
-        let slot = stem.strip_prefix("Profile")?.parse().ok()?;
-
-        Some(SaveHandle {
-            path,
-            slot,
-            backup_index: bak,
-        })
-    }
-
-    pub fn path(&self) -> &Path {
-        &self.path
-    }
-    pub fn slot(&self) -> u32 {
-        self.slot
-    }
-    pub fn backup_index(&self) -> Option<u32> {
-        self.backup_index
-    }
-
-    pub fn read(&self) -> Result<(saves::Savefile, LuaValue<'static>)> {
-        let data = std::fs::read(&self.path)?;
-        let result = saves::Savefile::parse(&data)?;
-        Ok(result)
-    }
-
-    pub fn read_header_only(&self) -> Result<saves::Savefile> {
-        let data = std::fs::read(&self.path)?;
-        let savefile = saves::Savefile::parse_header_only(&data)?;
-        Ok(savefile)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::path::PathBuf;
-
-    use crate::SaveHandle;
-
-    #[test]
-    fn save_handle_ok() {
-        let path = PathBuf::from("Profile1.sav");
-        assert_eq!(
-            SaveHandle::from_path(path.clone()).unwrap(),
-            SaveHandle {
-                path: path,
-                slot: 1,
-                backup_index: None
-            }
-        );
-
-        let path = PathBuf::from("Profile10.sav");
-        assert_eq!(
-            SaveHandle::from_path(path.clone()).unwrap(),
-            SaveHandle {
-                path: path,
-                slot: 10,
-                backup_index: None
-            }
-        );
-    }
-
-    #[test]
-    fn save_handle_invalid_ext() {
-        let path = PathBuf::from("Profile1.sjson");
-        assert_eq!(SaveHandle::from_path(path.clone()), None);
-    }
-
-    #[test]
-    fn save_handle_bak() {
-        let path = PathBuf::from("Profile1.sav.bak1");
-        assert_eq!(
-            SaveHandle::from_path(path.clone()).unwrap(),
-            SaveHandle {
-                path: path,
-                slot: 1,
-                backup_index: Some(1)
-            }
-        );
-
-        let path = PathBuf::from("Profile2.sav.bak10");
-        assert_eq!(
-            SaveHandle::from_path(path.clone()).unwrap(),
-            SaveHandle {
-                path: path,
-                slot: 2,
-                backup_index: Some(10)
-            }
-        );
     }
 }
@@ -0,0 +1,180 @@
+//! Comparison and hashing of lua values which treat tables as maps, independent of their entry order.
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use super::luabins::{LuaTable, Value};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64 bit FNV-1a, which unlike [`std::hash::DefaultHasher`] is stable across platforms and releases
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Fnv(FNV_OFFSET)
+    }
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+    pub(crate) fn write_u64(&mut self, val: u64) {
+        self.write(&val.to_le_bytes());
+    }
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Maps `-0.0` to `0.0` and every NaN to the same NaN, so that numbers can be compared by their bits
+fn normalize(val: f64) -> f64 {
+    match val {
+        _ if val.is_nan() => f64::NAN,
+        0.0 => 0.0,
+        _ => val,
+    }
+}
+
+/// Entries of `table` sorted by [`Value::canonical_cmp`] on their keys
+pub(crate) fn sorted_entries<'t, 'a>(table: &'t LuaTable<'a>) -> Vec<&'t (Value<'a>, Value<'a>)> {
+    let mut entries: Vec<_> = table.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.canonical_cmp(b));
+    entries
+}
+
+impl Value<'_> {
+    /// Total order on values, treating tables as maps.
+    ///
+    /// Unlike [`Ord`], the order of table entries doesn't matter, `-0.0` equals `0.0` and all NaNs are equal.
+    pub fn canonical_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => normalize(*a).total_cmp(&normalize(*b)),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Table(a), Value::Table(b)) => {
+                let (a, b) = (sorted_entries(a), sorted_entries(b));
+                a.len().cmp(&b.len()).then_with(|| {
+                    a.iter()
+                        .zip(&b)
+                        .map(|((a_key, a_val), (b_key, b_val))| {
+                            a_key
+                                .canonical_cmp(b_key)
+                                .then_with(|| a_val.canonical_cmp(b_val))
+                        })
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                })
+            }
+            _ => self.cmp(other),
+        }
+    }
+
+    /// Equality treating tables as maps, see [`Value::canonical_cmp`]
+    pub fn canonical_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Table(a), Value::Table(b)) if a.len() != b.len() => false,
+            _ => self.canonical_cmp(other).is_eq(),
+        }
+    }
+
+    /// Stable 64 bit hash of the value, which is the same for [`canonically equal`](Value::canonical_eq) values.
+    ///
+    /// The result doesn't depend on the platform or the order of table entries,
+    /// so it can be stored and compared across runs.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv::new();
+        match self {
+            Value::Nil => hasher.write(&[0]),
+            Value::Bool(val) => hasher.write(&[1, u8::from(*val)]),
+            Value::Number(val) => {
+                hasher.write(&[2]);
+                hasher.write_u64(normalize(*val).to_bits());
+            }
+            Value::String(val) => {
+                hasher.write(&[3]);
+                hasher.write_u64(val.len() as u64);
+                hasher.write(val.as_bytes());
+            }
+            Value::Table(table) => {
+                hasher.write(&[4]);
+                hasher.write_u64(table.len() as u64);
+                // entries are combined commutatively, so their order doesn't matter
+                let entries = table.iter().fold(0u64, |acc, (key, val)| {
+                    let mut entry = Fnv::new();
+                    entry.write_u64(key.fingerprint());
+                    entry.write_u64(val.fingerprint());
+                    acc.wrapping_add(entry.finish())
+                });
+                hasher.write_u64(entries);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// Consistent with both the derived, order-sensitive [`PartialEq`] and [`Value::canonical_eq`].
+impl Hash for Value<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.fingerprint());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::parser::luabins::Value;
+    use crate::parser::luatext::read_luatext;
+
+    #[test]
+    fn order_insensitive() {
+        let a = read_luatext(r#"{ a = 1, b = { 1, 2, x = "y" }, [3] = true }"#).unwrap();
+        let mut b =
+            read_luatext(r#"{ [3] = true, b = { x = "y", [2] = 2, [1] = 1 }, a = 1 }"#).unwrap();
+        b.as_table_mut().unwrap().0.reverse();
+
+        assert_ne!(a, b);
+        assert!(a.canonical_eq(&b));
+        assert_eq!(a.canonical_cmp(&b), Ordering::Equal);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        let c = read_luatext(r#"{ a = 1, b = { 1, 2, x = "z" }, [3] = true }"#).unwrap();
+        assert!(!a.canonical_eq(&c));
+        assert_ne!(a.fingerprint(), c.fingerprint());
+        assert_eq!(a.canonical_cmp(&c), c.canonical_cmp(&a).reverse());
+
+        // swapping values between keys changes the fingerprint
+        let d = read_luatext(r#"{ a = 1, b = 2 }"#).unwrap();
+        let e = read_luatext(r#"{ a = 2, b = 1 }"#).unwrap();
+        assert_ne!(d.fingerprint(), e.fingerprint());
+    }
+
+    #[test]
+    fn numbers() {
+        let zero = Value::Number(0.0);
+        let neg_zero = Value::Number(-0.0);
+        assert!(zero.canonical_eq(&neg_zero));
+        assert_eq!(zero.fingerprint(), neg_zero.fingerprint());
+
+        let nan = Value::Number(f64::NAN);
+        let other_nan = Value::Number(-f64::NAN);
+        assert!(nan.canonical_eq(&other_nan));
+        assert_eq!(nan.fingerprint(), other_nan.fingerprint());
+        assert_eq!(
+            Value::Number(1.0).canonical_cmp(&Value::Number(f64::INFINITY)),
+            Ordering::Less
+        );
+        assert!(!Value::Number(1.0).canonical_eq(&Value::String("1".into())));
+    }
+
+    #[test]
+    fn fingerprint_stable() {
+        assert_eq!(Value::Nil.fingerprint(), 0xaf63bd4c8601b7df);
+        assert_eq!(
+            read_luatext("{ 1 }").unwrap().fingerprint(),
+            read_luatext("{ [1] = 1 }").unwrap().fingerprint()
+        );
+    }
+}
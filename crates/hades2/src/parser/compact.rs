@@ -0,0 +1,527 @@
+//! Compact, read-only storage for lua states.
+//!
+//! A [`LuaValue`](super::luabins::Value) tree allocates every string and table separately.
+//! [`CompactState`] instead stores all table entries in one flat array and every distinct string
+//! once, so a full state needs a handful of allocations and a fraction of the memory.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::luabins::{LuaTable, Value, number_to_string};
+use super::path::LuaPath;
+use super::{Error, Result, read_f64, read_str_prefix, read_u8, read_u32};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct StrId(u32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Node {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(StrId),
+    /// Entries are stored at `entries[start..start + len]`
+    Table {
+        start: u32,
+        len: u32,
+    },
+}
+
+#[derive(Clone, Default)]
+struct Strings {
+    data: String,
+    spans: Vec<(u32, u32)>,
+}
+
+impl Strings {
+    fn get(&self, id: StrId) -> &str {
+        let (start, len) = self.spans[id.0 as usize];
+        &self.data[start as usize..(start + len) as usize]
+    }
+}
+
+/// Lua state stored in a flat array with interned strings, see the [module docs](self).
+///
+/// Values are accessed through [`NodeRef`]s, starting at [`CompactState::root`].
+#[derive(Clone)]
+pub struct CompactState {
+    strings: Strings,
+    entries: Vec<(Node, Node)>,
+    root: Node,
+}
+
+struct Builder<'s> {
+    strings: Strings,
+    lookup: HashMap<&'s str, StrId>,
+    entries: Vec<(Node, Node)>,
+}
+
+impl<'s> Builder<'s> {
+    fn new() -> Self {
+        Builder {
+            strings: Strings::default(),
+            lookup: HashMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    fn intern(&mut self, str: &'s str) -> StrId {
+        *self.lookup.entry(str).or_insert_with(|| {
+            let id = StrId(self.strings.spans.len() as u32);
+            let start = self.strings.data.len() as u32;
+            self.strings.data.push_str(str);
+            self.strings.spans.push((start, str.len() as u32));
+            id
+        })
+    }
+
+    fn read_node(&mut self, data: &mut &'s [u8]) -> Result<Node> {
+        let ty = read_u8(data)?;
+        Ok(match ty {
+            b'-' => Node::Nil,
+            b'0' => Node::Bool(false),
+            b'1' => Node::Bool(true),
+            b'N' => Node::Number(read_f64(data)?),
+            b'S' => Node::String(self.intern(read_str_prefix(data)?)),
+            b'T' => {
+                let array_size = read_u32(data)?;
+                let hash_size = read_u32(data)?;
+                let len = array_size + hash_size;
+
+                // children are appended after this table's entries
+                let start = self.entries.len();
+                self.entries
+                    .resize(start + len as usize, (Node::Nil, Node::Nil));
+                for i in start..start + len as usize {
+                    let key = self.read_node(data)?;
+                    let val = self.read_node(data)?;
+                    self.entries[i] = (key, val);
+                }
+
+                // same order as `LuaTable::sort`
+                let end = start + len as usize;
+                let (entries, nested) = self.entries.split_at_mut(end);
+                let cmp = NodeCmp {
+                    strings: &self.strings,
+                    nested,
+                    offset: end,
+                };
+                entries[start..].sort_by(|(a, a_val), (b, b_val)| {
+                    let primitive_first = is_primitive(*b_val).cmp(&is_primitive(*a_val));
+                    primitive_first.then_with(|| cmp.cmp(*a, *b))
+                });
+
+                Node::Table {
+                    start: start as u32,
+                    len,
+                }
+            }
+            _ => return Err(Error::Lua),
+        })
+    }
+
+    fn add_value(&mut self, value: &'s Value<'_>) -> Node {
+        match value {
+            Value::Nil => Node::Nil,
+            Value::Bool(val) => Node::Bool(*val),
+            Value::Number(val) => Node::Number(*val),
+            Value::String(val) => Node::String(self.intern(val)),
+            Value::Table(table) => {
+                let start = self.entries.len();
+                self.entries
+                    .resize(start + table.len(), (Node::Nil, Node::Nil));
+                for (i, (key, val)) in table.iter().enumerate() {
+                    let key = self.add_value(key);
+                    let val = self.add_value(val);
+                    self.entries[start + i] = (key, val);
+                }
+                Node::Table {
+                    start: start as u32,
+                    len: table.len() as u32,
+                }
+            }
+        }
+    }
+
+    fn finish(mut self, root: Node) -> CompactState {
+        self.strings.data.shrink_to_fit();
+        self.strings.spans.shrink_to_fit();
+        self.entries.shrink_to_fit();
+        CompactState {
+            strings: self.strings,
+            entries: self.entries,
+            root,
+        }
+    }
+}
+
+fn is_primitive(node: Node) -> bool {
+    !matches!(node, Node::Table { .. })
+}
+
+/// Implements the [`Ord`] of [`Value`] on nodes of a table which is still being sorted
+struct NodeCmp<'a> {
+    strings: &'a Strings,
+    /// Entries after the table, where all nested tables are stored
+    nested: &'a [(Node, Node)],
+    offset: usize,
+}
+
+impl NodeCmp<'_> {
+    fn cmp(&self, a: Node, b: Node) -> Ordering {
+        let discriminant = |node: Node| match node {
+            Node::Nil => 0,
+            Node::Bool(_) => 1,
+            Node::Number(_) => 2,
+            Node::String(_) => 3,
+            Node::Table { .. } => 4,
+        };
+
+        match (a, b) {
+            (Node::Bool(a), Node::Bool(b)) => a.cmp(&b),
+            (Node::Number(a), Node::Number(b)) => a.total_cmp(&b),
+            (Node::String(a), Node::String(b)) => {
+                let a = Value::String(Cow::Borrowed(self.strings.get(a)));
+                let b = Value::String(Cow::Borrowed(self.strings.get(b)));
+                a.cmp(&b)
+            }
+            (
+                Node::Table {
+                    start: a,
+                    len: a_len,
+                },
+                Node::Table {
+                    start: b,
+                    len: b_len,
+                },
+            ) => {
+                let a = &self.nested[a as usize - self.offset..][..a_len as usize];
+                let b = &self.nested[b as usize - self.offset..][..b_len as usize];
+                a.iter()
+                    .zip(b)
+                    .map(|((a_key, a_val), (b_key, b_val))| {
+                        self.cmp(*a_key, *b_key)
+                            .then_with(|| self.cmp(*a_val, *b_val))
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| a.len().cmp(&b.len()))
+            }
+            _ => discriminant(a).cmp(&discriminant(b)),
+        }
+    }
+}
+
+impl CompactState {
+    /// Reads a luabins blob containing a single value, like the lua state of a savefile.
+    ///
+    /// Tables are sorted like in [`read_luabins`](super::luabins::read_luabins).
+    pub fn read_luabins(mut data: &[u8]) -> Result<CompactState> {
+        let data = &mut data;
+        if read_u8(data)? != 1 {
+            return Err(Error::Lua);
+        }
+
+        let mut builder = Builder::new();
+        let root = builder.read_node(data)?;
+        if !data.is_empty() {
+            return Err(Error::UnexpectedAtEnd);
+        }
+
+        Ok(builder.finish(root))
+    }
+
+    pub fn from_value(value: &Value<'_>) -> CompactState {
+        let mut builder = Builder::new();
+        let root = builder.add_value(value);
+        builder.finish(root)
+    }
+
+    /// Converts back into a [`Value`], borrowing the strings from `self`
+    pub fn to_value(&self) -> Value<'_> {
+        self.root().to_value()
+    }
+
+    pub fn root(&self) -> NodeRef<'_> {
+        NodeRef {
+            state: self,
+            node: self.root,
+        }
+    }
+
+    /// Number of distinct strings
+    pub fn string_count(&self) -> usize {
+        self.strings.spans.len()
+    }
+
+    /// Approximate number of bytes allocated on the heap
+    pub fn heap_bytes(&self) -> usize {
+        self.strings.data.capacity()
+            + self.strings.spans.capacity() * size_of::<(u32, u32)>()
+            + self.entries.capacity() * size_of::<(Node, Node)>()
+    }
+}
+
+impl From<&Value<'_>> for CompactState {
+    fn from(value: &Value<'_>) -> Self {
+        CompactState::from_value(value)
+    }
+}
+
+impl fmt::Debug for CompactState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.root(), f)
+    }
+}
+
+/// Reference to a value inside a [`CompactState`], with the read-only API of [`Value`].
+#[derive(Clone, Copy)]
+pub struct NodeRef<'s> {
+    state: &'s CompactState,
+    node: Node,
+}
+
+impl<'s> NodeRef<'s> {
+    pub fn as_number(&self) -> Option<f64> {
+        match self.node {
+            Node::Number(val) => Some(val),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node {
+            Node::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&'s str> {
+        match self.node {
+            Node::String(id) => Some(self.state.strings.get(id)),
+            _ => None,
+        }
+    }
+    pub fn is_nil(&self) -> bool {
+        matches!(self.node, Node::Nil)
+    }
+    pub fn is_table(&self) -> bool {
+        matches!(self.node, Node::Table { .. })
+    }
+    pub fn is_primitive(&self) -> bool {
+        is_primitive(self.node)
+    }
+    pub fn is_str(&self, val: &str) -> bool {
+        self.as_str() == Some(val)
+    }
+
+    fn entries(&self) -> &'s [(Node, Node)] {
+        match self.node {
+            Node::Table { start, len } => {
+                &self.state.entries[start as usize..(start + len) as usize]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Number of table entries, `0` for primitives
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Iterates over the entries of a table, or nothing for primitives
+    pub fn iter(&self) -> impl Iterator<Item = (NodeRef<'s>, NodeRef<'s>)> + use<'s> {
+        let state = self.state;
+        self.entries()
+            .iter()
+            .map(move |&(key, val)| (NodeRef { state, node: key }, NodeRef { state, node: val }))
+    }
+
+    pub fn get(&self, key: &str) -> Option<NodeRef<'s>> {
+        self.iter().find(|(k, _)| k.is_str(key)).map(|(_, val)| val)
+    }
+    pub fn find(&self, key: &Value) -> Option<NodeRef<'s>> {
+        self.iter()
+            .find(|(k, _)| k.eq_value(key))
+            .map(|(_, val)| val)
+    }
+
+    /// Looks up a [`LuaPath`] like `GameState.Resources["Key With Dots"][3]`.
+    /// Returns `None` if the path is invalid or doesn't exist.
+    pub fn get_path(&self, path: &str) -> Option<NodeRef<'s>> {
+        self.resolve(&path.parse().ok()?)
+    }
+    pub fn resolve(&self, path: &LuaPath) -> Option<NodeRef<'s>> {
+        path.segments()
+            .iter()
+            .try_fold(*self, |acc, key| acc.find(key))
+    }
+
+    fn eq_value(&self, value: &Value) -> bool {
+        match (self.node, value) {
+            (Node::Nil, Value::Nil) => true,
+            (Node::Bool(a), Value::Bool(b)) => a == *b,
+            (Node::Number(a), Value::Number(b)) => a == *b,
+            (Node::String(_), Value::String(b)) => self.as_str() == Some(b),
+            (Node::Table { .. }, Value::Table(b)) => {
+                self.len() == b.len()
+                    && self
+                        .iter()
+                        .zip(b.iter())
+                        .all(|((a_key, a_val), (b_key, b_val))| {
+                            a_key.eq_value(b_key) && a_val.eq_value(b_val)
+                        })
+            }
+            _ => false,
+        }
+    }
+
+    pub fn primitive_to_str(&self) -> Option<Cow<'s, str>> {
+        Some(match self.node {
+            Node::Nil => "Nil".into(),
+            Node::Bool(val) => val.to_string().into(),
+            Node::Number(val) => number_to_string(val).into(),
+            Node::String(id) => Cow::Borrowed(self.state.strings.get(id)),
+            Node::Table { .. } => return None,
+        })
+    }
+
+    /// Converts into a [`Value`], borrowing the strings from the [`CompactState`]
+    pub fn to_value(&self) -> Value<'s> {
+        match self.node {
+            Node::Nil => Value::Nil,
+            Node::Bool(val) => Value::Bool(val),
+            Node::Number(val) => Value::Number(val),
+            Node::String(id) => Value::String(Cow::Borrowed(self.state.strings.get(id))),
+            Node::Table { .. } => Value::Table(LuaTable(
+                self.iter()
+                    .map(|(key, val)| (key.to_value(), val.to_value()))
+                    .collect(),
+            )),
+        }
+    }
+
+    pub fn visit(&self, include_keys: bool, f: &mut impl FnMut(NodeRef<'s>)) {
+        for (key, val) in self.iter() {
+            if include_keys {
+                key.visit(include_keys, f);
+            }
+            val.visit(include_keys, f);
+        }
+        f(*self);
+    }
+
+    pub fn count(&self, include_keys: bool, f: &mut impl FnMut(NodeRef<'s>) -> bool) -> usize {
+        let mut i = 0;
+        self.visit(include_keys, &mut |value| {
+            if f(value) {
+                i += 1;
+            }
+        });
+        i
+    }
+}
+
+impl PartialEq<Value<'_>> for NodeRef<'_> {
+    fn eq(&self, other: &Value<'_>) -> bool {
+        self.eq_value(other)
+    }
+}
+
+impl fmt::Debug for NodeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.node {
+            Node::Nil => write!(f, "Nil"),
+            Node::Bool(val) => write!(f, "{val}"),
+            Node::Number(val) => f.write_str(&number_to_string(val)),
+            Node::String(id) => f.write_str(self.state.strings.get(id)),
+            Node::Table { .. } => f.debug_map().entries(self.iter()).finish(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for NodeRef<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.node {
+            Node::Nil => serializer.serialize_none(),
+            Node::Bool(val) => serializer.serialize_bool(val),
+            Node::Number(val) => super::luabins::serialize_number(val, serializer),
+            Node::String(id) => serializer.serialize_str(self.state.strings.get(id)),
+            Node::Table { .. } => serializer.collect_map(self.iter()),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CompactState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.root().serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompactState;
+    use crate::analysis::heap_bytes;
+    use crate::parser::luatext::read_luatext;
+    use crate::saves::Savefile;
+
+    const TEST_PROFILE_V17: &[u8] =
+        include_bytes!("../../../../testdata/Profile.v17.sav").as_slice();
+    const TEST_PROFILE_V18: &[u8] =
+        include_bytes!("../../../../testdata/Profile.v18.sav").as_slice();
+
+    #[test]
+    fn compact_matches_value() {
+        for data in [TEST_PROFILE_V17, TEST_PROFILE_V18] {
+            let (_, lua_state) = Savefile::parse(data).unwrap();
+            let (_, compact) = Savefile::parse_compact(data).unwrap();
+
+            assert_eq!(compact.to_value(), lua_state);
+            assert!(compact.root() == lua_state);
+            assert_eq!(CompactState::from_value(&lua_state).to_value(), lua_state);
+            assert!(compact.heap_bytes() < heap_bytes(&lua_state) * 2 / 3);
+
+            let resources = compact.root().get_path("GameState.Resources").unwrap();
+            assert_eq!(
+                resources
+                    .get("CardUpgradePoints")
+                    .and_then(|v| v.as_number()),
+                lua_state
+                    .get_path("GameState.Resources.CardUpgradePoints")
+                    .and_then(|v| v.as_number())
+            );
+            assert_eq!(
+                compact.root().count(true, &mut |_| true),
+                lua_state.count(true, &mut |_| true)
+            );
+        }
+    }
+
+    #[test]
+    fn short_input() {
+        for len in [0, 4, 7, 8, 16] {
+            let data = &TEST_PROFILE_V18[..len];
+            assert!(Savefile::parse_compact(data).is_err());
+            assert!(Savefile::parse(data).is_err());
+        }
+    }
+
+    #[test]
+    fn compact_query() {
+        let value = read_luatext(r#"{ a = { "x", "x", b = true }, [2.5] = { } }"#).unwrap();
+        let compact = CompactState::from(&value);
+        assert_eq!(compact.string_count(), 3);
+
+        let root = compact.root();
+        assert_eq!(root.len(), 2);
+        assert_eq!(root.get_path("a[2]").and_then(|v| v.as_str()), Some("x"));
+        assert_eq!(root.get_path("a.b").and_then(|v| v.as_bool()), Some(true));
+        assert!(root.get_path("[2.5]").unwrap().is_table());
+        assert!(root.get_path("a.c").is_none());
+        assert_eq!(format!("{root:?}"), format!("{value:?}"));
+    }
+}
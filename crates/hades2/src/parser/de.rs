@@ -0,0 +1,414 @@
+//! Reading lua values with serde.
+//!
+//! [`Value`] implements [`Deserialize`], and `&Value` is a [`Deserializer`] itself, so subtrees
+//! of a lua state can be mapped onto regular `#[derive(Deserialize)]` types using [`from_lua`].
+//!
+//! Tables whose keys are exactly `1..=n` are sequences, all other tables are maps.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, Deserialize, Deserializer, IntoDeserializer, Unexpected, Visitor};
+
+use super::luabins::{LuaTable, Value, lossless_integer};
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Error(String);
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Deserializes a `T` from a lua value, borrowing strings where possible.
+pub fn from_lua<'de, T: Deserialize<'de>>(value: &'de Value<'_>) -> Result<T, Error> {
+    T::deserialize(value)
+}
+
+impl<'de> Deserialize<'de> for Value<'static> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value<'static>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a lua value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::Number(v as f64))
+    }
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Value::Number(v as f64))
+    }
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::Number(v))
+    }
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::String(Cow::Owned(v.to_owned())))
+    }
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Value::String(Cow::Owned(v)))
+    }
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Nil)
+    }
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Nil)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            let key = Value::Number((entries.len() + 1) as f64);
+            entries.push((key, value));
+        }
+
+        let mut table = LuaTable(entries);
+        table.sort();
+        Ok(Value::Table(table))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        let mut table = LuaTable(entries);
+        table.sort();
+        Ok(Value::Table(table))
+    }
+}
+
+/// Returns the values of the table ordered by key, if its keys are exactly `1..=n`
+fn sequence<'a, 'l>(table: &'a LuaTable<'l>) -> Option<Vec<&'a Value<'l>>> {
+    let mut items = vec![None; table.len()];
+    for (key, value) in table.iter() {
+        let index = key.as_i64()?;
+        let slot = items.get_mut(usize::try_from(index).ok()?.checked_sub(1)?)?;
+        if slot.replace(value).is_some() {
+            return None;
+        }
+    }
+    items.into_iter().collect()
+}
+
+fn unexpected<'a>(value: &'a Value<'_>) -> Unexpected<'a> {
+    match value {
+        Value::Nil => Unexpected::Unit,
+        Value::Bool(val) => Unexpected::Bool(*val),
+        Value::Number(val) => Unexpected::Float(*val),
+        Value::String(val) => Unexpected::Str(val),
+        Value::Table(_) => Unexpected::Map,
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for &'de Value<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let int = self
+                .as_i64()
+                .and_then(|int| <$ty>::try_from(int).ok());
+            match int {
+                Some(int) => visitor.$visit(int),
+                None => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+            }
+        })*
+    };
+}
+
+impl<'de> Deserializer<'de> for &'de Value<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            Value::Bool(val) => visitor.visit_bool(*val),
+            Value::Number(val) => match lossless_integer(*val) {
+                Some(int) => visitor.visit_i64(int),
+                None => visitor.visit_f64(*val),
+            },
+            Value::String(val) => visitor.visit_borrowed_str(val),
+            Value::Table(table) => match sequence(table) {
+                Some(items) => visitor.visit_seq(SeqDeserializer::new(items.into_iter())),
+                None => self.deserialize_map(visitor),
+            },
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Number(val) => visitor.visit_f64(*val),
+            _ => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.as_table().and_then(sequence) {
+            Some(items) => visitor.visit_seq(SeqDeserializer::new(items.into_iter())),
+            None => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Table(table) => {
+                let entries = table.iter().map(|(key, val)| (key, val));
+                visitor.visit_map(MapDeserializer::new(entries))
+            }
+            _ => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(_) => visitor.visit_enum(EnumDeserializer {
+                variant: self,
+                value: None,
+            }),
+            Value::Table(LuaTable(entries)) if entries.len() == 1 => {
+                let (variant, value) = &entries[0];
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            _ => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            _ => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool char str string bytes byte_buf identifier
+    }
+}
+
+struct EnumDeserializer<'de, 'l> {
+    variant: &'de Value<'l>,
+    value: Option<&'de Value<'l>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer<'de, '_> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.variant)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EnumDeserializer<'de, '_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            None | Some(Value::Nil) => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(unexpected(value), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Some(value) => value.deserialize_tuple(len, visitor),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Some(value) => value.deserialize_struct("", fields, visitor),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::from_lua;
+    use crate::parser::luabins::Value;
+    use crate::parser::luatext::read_luatext;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Resources<'a> {
+        #[serde(rename = "CardUpgradePoints")]
+        ash: u32,
+        #[serde(rename = "MetaCurrency")]
+        bones: Option<u32>,
+        #[serde(borrow)]
+        names: Vec<&'a str>,
+        rest: HashMap<String, f64>,
+        kind: Kind,
+        other: Kind,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Unit,
+        Newtype(f32),
+    }
+
+    #[test]
+    fn deserialize_struct() {
+        let value = read_luatext(
+            r#"{
+                CardUpgradePoints = 20,
+                names = { "a", "b" },
+                rest = { a = 1.5 },
+                kind = "Unit",
+                other = { Newtype = 2 },
+            }"#,
+        )
+        .unwrap();
+
+        let resources: Resources = from_lua(&value).unwrap();
+        assert_eq!(
+            resources,
+            Resources {
+                ash: 20,
+                bones: None,
+                names: vec!["a", "b"],
+                rest: HashMap::from([("a".to_owned(), 1.5)]),
+                kind: Kind::Unit,
+                other: Kind::Newtype(2.0),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let value = read_luatext("{ 1.5, [3] = 3 }").unwrap();
+        assert!(from_lua::<Vec<f64>>(&value).is_err());
+
+        let value = read_luatext("{ -1 }").unwrap();
+        assert!(from_lua::<Vec<u32>>(&value).is_err());
+        assert_eq!(from_lua::<Vec<i32>>(&value).unwrap(), vec![-1]);
+    }
+
+    #[test]
+    fn value_roundtrip() {
+        let value = read_luatext(r#"{ a = { 1, 2, "x" }, b = true, c = 2.5 }"#).unwrap();
+        let reparsed: Value = from_lua(&value).unwrap();
+        assert_eq!(value, reparsed);
+    }
+}
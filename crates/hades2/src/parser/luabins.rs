@@ -23,18 +23,34 @@ impl<'a> LuaTable<'a> {
         self.0.iter_mut()
     }
 
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        self.0.iter().find(|(k, _)| k.is_str(key)).map(|(_, v)| v)
+    }
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'a>> {
+        self.0
+            .iter_mut()
+            .find(|(k, _)| k.is_str(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn find(&self, key: &Value) -> Option<&Value<'a>> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+    pub fn find_mut(&mut self, key: &Value) -> Option<&mut Value<'a>> {
+        self.0.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// The entry at `key`, or `insert` added in sorted position if there is none
     pub fn get_or_insert(&mut self, key: &str, insert: Value<'a>) -> &mut Value<'a> {
-        let pos = self
-            .0
-            .iter()
-            .position(|(k, _)| k.is_str(key))
-            .unwrap_or_else(|| {
-                let i = self.0.len();
-                self.0.push((Value::String(key.to_owned().into()), insert));
-                i
-            });
-        self.sort();
-        &mut self.0[pos].1
+        if self.get(key).is_none() {
+            self.0.push((Value::String(key.to_owned().into()), insert));
+            self.sort();
+        }
+        self.get_mut(key).unwrap()
+    }
+    pub fn remove(&mut self, key: &str) -> Option<Value<'a>> {
+        let pos = self.0.iter().position(|(k, _)| k.is_str(key))?;
+        Some(self.0.remove(pos).1)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -96,7 +112,7 @@ impl<'a> std::fmt::Debug for Value<'a> {
         match self {
             Self::Nil => write!(f, "Nil"),
             Self::Bool(val) => write!(f, "{val}"),
-            Self::Number(val) => write!(f, "{val}"),
+            Self::Number(val) => f.write_str(&number_to_string(*val)),
             Self::String(val) => f.write_str(val),
             // Self::Table(val) => f.debug_tuple("Table").field(val).finish(),
             Self::Table(table) => {
@@ -110,6 +126,36 @@ impl<'a> std::fmt::Debug for Value<'a> {
     }
 }
 
+/// Every integer up to this magnitude can be represented exactly by a lua number
+pub const MAX_EXACT_INTEGER: i64 = 1 << f64::MANTISSA_DIGITS;
+
+pub(crate) fn exact_integer(val: f64) -> Option<i64> {
+    (val.fract() == 0.0 && val.abs() <= MAX_EXACT_INTEGER as f64).then_some(val as i64)
+}
+
+/// Like [`exact_integer`], but keeps `-0.0` as a float so that its sign isn't lost when displaying or serializing
+pub(crate) fn lossless_integer(val: f64) -> Option<i64> {
+    exact_integer(val).filter(|_| !(val == 0.0 && val.is_sign_negative()))
+}
+
+pub(crate) fn number_to_string(val: f64) -> String {
+    match lossless_integer(val) {
+        Some(int) => int.to_string(),
+        None => val.to_string(),
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_number<S: serde::Serializer>(
+    val: f64,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match lossless_integer(val) {
+        Some(int) => serializer.serialize_i64(int),
+        None => serializer.serialize_f64(val),
+    }
+}
+
 impl<'l> Value<'l> {
     pub const EMPTY_TABLE: Self = Value::Table(LuaTable(Vec::new()));
 
@@ -119,6 +165,18 @@ impl<'l> Value<'l> {
             _ => None,
         }
     }
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(val) => Some(val),
+            _ => None,
+        }
+    }
     pub fn as_number_mut(&mut self) -> Option<&mut f64> {
         match self {
             Value::Number(val) => Some(val),
@@ -126,6 +184,33 @@ impl<'l> Value<'l> {
         }
     }
 
+    /// Returns the number as an integer, if it is integral and exactly representable,
+    /// i.e. within `±`[`MAX_EXACT_INTEGER`]
+    pub fn as_i64(&self) -> Option<i64> {
+        exact_integer(self.as_number()?)
+    }
+    pub fn as_u32(&self) -> Option<u32> {
+        self.as_i64()?.try_into().ok()
+    }
+    pub fn is_integer(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Overwrites a number.
+    ///
+    /// Fails if `self` is not a number, or if `self` is an integer (usually a counter or ID)
+    /// and `val` is not.
+    pub fn set_number(&mut self, val: f64) -> Result<()> {
+        let Value::Number(current) = self else {
+            return Err(Error::NotANumber);
+        };
+        if exact_integer(*current).is_some() && exact_integer(val).is_none() {
+            return Err(Error::NotAnInteger(val));
+        }
+        *current = val;
+        Ok(())
+    }
+
     pub fn as_table(&self) -> Option<&LuaTable<'l>> {
         match self {
             Value::Table(entries) => Some(entries),
@@ -139,6 +224,22 @@ impl<'l> Value<'l> {
         }
     }
 
+    pub fn get(&self, key: &str) -> Option<&Value<'l>> {
+        self.as_table()?.get(key)
+    }
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'l>> {
+        self.as_table_mut()?.get_mut(key)
+    }
+
+    /// Looks up a [`LuaPath`](super::path::LuaPath) like `GameState.Resources["Key With Dots"][3]`.
+    /// Returns `None` if the path is invalid or doesn't exist.
+    pub fn get_path(&self, path: &str) -> Option<&Value<'l>> {
+        path.parse::<super::path::LuaPath>().ok()?.resolve(self)
+    }
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value<'l>> {
+        path.parse::<super::path::LuaPath>().ok()?.resolve_mut(self)
+    }
+
     pub fn visit(&self, include_keys: bool, f: &mut impl FnMut(&Value<'_>)) {
         match self {
             Value::Nil => {}
@@ -157,6 +258,38 @@ impl<'l> Value<'l> {
         f(self);
     }
 
+    /// Clones the value, borrowing its strings from `self`
+    pub fn borrowed(&self) -> Value<'_> {
+        match self {
+            Value::Nil => Value::Nil,
+            Value::Bool(val) => Value::Bool(*val),
+            Value::Number(val) => Value::Number(*val),
+            Value::String(val) => Value::String(Cow::Borrowed(val)),
+            Value::Table(table) => Value::Table(LuaTable(
+                table
+                    .iter()
+                    .map(|(key, val)| (key.borrowed(), val.borrowed()))
+                    .collect(),
+            )),
+        }
+    }
+
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Nil => Value::Nil,
+            Value::Bool(val) => Value::Bool(val),
+            Value::Number(val) => Value::Number(val),
+            Value::String(val) => Value::String(Cow::Owned(val.into_owned())),
+            Value::Table(table) => Value::Table(LuaTable(
+                table
+                    .0
+                    .into_iter()
+                    .map(|(key, val)| (key.into_owned(), val.into_owned()))
+                    .collect(),
+            )),
+        }
+    }
+
     pub fn is_primitive(&self) -> bool {
         !matches!(self, Value::Table(_))
     }
@@ -172,7 +305,7 @@ impl<'l> Value<'l> {
         Some(match self {
             Value::Nil => "Nil".into(),
             Value::Bool(val) => val.to_string().into(),
-            Value::Number(val) => val.to_string().into(),
+            Value::Number(val) => number_to_string(*val).into(),
             Value::String(val) => Cow::Borrowed(val.as_ref()),
             Value::Table(_) => return None,
         })
@@ -190,6 +323,15 @@ impl<'l> Value<'l> {
 }
 
 pub fn read_luabins(data: &mut &[u8]) -> Result<Vec<Value<'static>>> {
+    read_luabins_with(data, &mut |str| Cow::Owned(str.to_owned()))
+}
+
+/// Like [`read_luabins`], but every string is turned into a value by `string`,
+/// which can e.g. [intern](super::intern::Interner) them.
+pub fn read_luabins_with<'i>(
+    data: &mut &[u8],
+    string: &mut impl FnMut(&str) -> Cow<'i, str>,
+) -> Result<Vec<Value<'i>>> {
     let len = read_u8(data)?;
 
     if len > 250 {
@@ -198,7 +340,7 @@ pub fn read_luabins(data: &mut &[u8]) -> Result<Vec<Value<'static>>> {
 
     let mut values = Vec::with_capacity(len as usize);
     for _ in 0..len {
-        let val = read_value(data)?;
+        let val = read_value_with(data, string)?;
         values.push(val);
     }
 
@@ -209,7 +351,15 @@ pub fn read_luabins(data: &mut &[u8]) -> Result<Vec<Value<'static>>> {
     Ok(values)
 }
 
+#[cfg(test)]
 pub fn read_value(data: &mut &[u8]) -> Result<Value<'static>> {
+    read_value_with(data, &mut |str| Cow::Owned(str.to_owned()))
+}
+
+fn read_value_with<'i>(
+    data: &mut &[u8],
+    string: &mut impl FnMut(&str) -> Cow<'i, str>,
+) -> Result<Value<'i>> {
     let ty = read_u8(data)?;
     let val = match ty {
         b'-' => Value::Nil,
@@ -221,8 +371,7 @@ pub fn read_value(data: &mut &[u8]) -> Result<Value<'static>> {
         }
         b'S' => {
             let str = read_str_prefix(data)?;
-            // Value::String(Cow::Borrowed(str))
-            Value::String(Cow::Owned(str.to_owned()))
+            Value::String(string(str))
         }
         b'T' => {
             let array_size = read_u32(data)?;
@@ -232,8 +381,8 @@ pub fn read_value(data: &mut &[u8]) -> Result<Value<'static>> {
             let mut pairs = Vec::with_capacity(total_size as usize);
 
             for _ in 0..total_size {
-                let key = read_value(data)?;
-                let val = read_value(data)?;
+                let key = read_value_with(data, string)?;
+                let val = read_value_with(data, string)?;
 
                 pairs.push((key, val));
             }
@@ -271,7 +420,7 @@ impl serde::Serialize for Value<'_> {
         match *self {
             Value::Nil => serializer.serialize_none(),
             Value::Bool(val) => serializer.serialize_bool(val),
-            Value::Number(val) => serializer.serialize_f64(val),
+            Value::Number(val) => serialize_number(val, serializer),
             Value::String(ref val) => serializer.serialize_str(val),
             Value::Table(ref table) => {
                 use serde::ser::SerializeMap;
@@ -393,3 +542,67 @@ pub mod write {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{LuaTable, MAX_EXACT_INTEGER, Value};
+    use crate::parser::Error;
+
+    #[test]
+    fn get_or_insert() {
+        let mut table = LuaTable(vec![
+            (Value::String("b".into()), Value::Number(1.0)),
+            (Value::String("c".into()), Value::Number(2.0)),
+        ]);
+        // sorts before the existing keys, so it doesn't stay where it was pushed
+        *table.get_or_insert("a", Value::Nil) = Value::Number(0.0);
+        assert_eq!(table.get("a"), Some(&Value::Number(0.0)));
+        assert_eq!(table.get("c"), Some(&Value::Number(2.0)));
+        assert_eq!(table.0[0].0, Value::String("a".into()));
+
+        *table.get_or_insert("b", Value::Nil) = Value::Number(3.0);
+        assert_eq!(table.get("b"), Some(&Value::Number(3.0)));
+        assert_eq!(table.len(), 3);
+
+        assert_eq!(table.remove("b"), Some(Value::Number(3.0)));
+        assert_eq!(table.remove("b"), None);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(Value::Number(3.0).as_i64(), Some(3));
+        assert_eq!(Value::Number(-3.0).as_u32(), None);
+        assert_eq!(Value::Number(3.5).as_i64(), None);
+        assert_eq!(Value::Number(f64::NAN).as_i64(), None);
+        assert_eq!(Value::Number(4294967296.0).as_u32(), None);
+        let max = MAX_EXACT_INTEGER as f64;
+        assert_eq!(Value::Number(max).as_i64(), Some(MAX_EXACT_INTEGER));
+        assert_eq!(Value::Number(max * 2.0).as_i64(), None);
+        assert_eq!(Value::String("3".into()).as_i64(), None);
+
+        assert_eq!(Value::Number(3.0).primitive_to_str().unwrap(), "3");
+        assert_eq!(
+            Value::Number(1e17).primitive_to_str().unwrap(),
+            "100000000000000000"
+        );
+        assert_eq!(Value::Number(-0.0).primitive_to_str().unwrap(), "-0");
+        assert_eq!(Value::Number(0.25).primitive_to_str().unwrap(), "0.25");
+    }
+
+    #[test]
+    fn set_number() {
+        let mut value = Value::Number(3.0);
+        value.set_number(4.0).unwrap();
+        assert!(matches!(value.set_number(4.5), Err(Error::NotAnInteger(_))));
+        assert_eq!(value, Value::Number(4.0));
+
+        let mut value = Value::Number(0.5);
+        value.set_number(2.0).unwrap();
+        assert!(value.set_number(2.5).is_err());
+
+        let mut value = Value::Number(0.5);
+        value.set_number(0.75).unwrap();
+        assert!(matches!(Value::Nil.set_number(1.0), Err(Error::NotANumber)));
+    }
+}
@@ -0,0 +1,512 @@
+//! Lua table constructor syntax for lua states, e.g.
+//! ```lua
+//! {
+//!     ["GameState"] = {
+//!         ["Resources"] = {
+//!             ["CardUpgradePoints"] = 20,
+//!         },
+//!     },
+//! }
+//! ```
+//!
+//! [`write_luatext`] always emits explicit `[key] = value` fields, so the output is lossless.
+//! [`read_luatext`] additionally accepts `Name = value` and positional fields, as well as comments.
+//! Non-finite numbers are written as `math.huge`, `-math.huge` and `0/0`.
+//! The only output which isn't valid lua is `[nil] = ...`, which can exist in savefiles but not in lua.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use super::luabins::{LuaTable, Value};
+use super::{Error, Result};
+
+const INDENT: &str = "    ";
+
+pub fn write_luatext(out: &mut String, value: &Value) {
+    write_value(out, value, 0);
+}
+
+fn write_value(out: &mut String, value: &Value, depth: usize) {
+    match value {
+        Value::Nil => out.push_str("nil"),
+        Value::Bool(val) => write!(out, "{val}").unwrap(),
+        Value::Number(val) => write_number(out, *val),
+        Value::String(val) => write_string(out, val),
+        Value::Table(table) if table.is_empty() => out.push_str("{}"),
+        Value::Table(table) => {
+            out.push_str("{\n");
+            for (key, val) in table {
+                for _ in 0..depth + 1 {
+                    out.push_str(INDENT);
+                }
+                out.push('[');
+                write_value(out, key, depth + 1);
+                out.push_str("] = ");
+                write_value(out, val, depth + 1);
+                out.push_str(",\n");
+            }
+            for _ in 0..depth {
+                out.push_str(INDENT);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(out: &mut String, val: f64) {
+    if val.is_nan() {
+        out.push_str("0/0");
+    } else if val == f64::INFINITY {
+        out.push_str("math.huge");
+    } else if val == f64::NEG_INFINITY {
+        out.push_str("-math.huge");
+    } else {
+        // rust's float formatting is the shortest representation which roundtrips
+        write!(out, "{val}").unwrap();
+    }
+}
+
+pub(crate) fn write_string(out: &mut String, val: &str) {
+    out.push('"');
+    for c in val.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_ascii_control() => write!(out, "\\{:03}", c as u8).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+pub fn read_luatext(text: &str) -> Result<Value<'static>> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_trivia()?;
+    let value = parser.value()?;
+    parser.skip_trivia()?;
+    if parser.pos != parser.text.len() {
+        return Err(parser.error("expected end of input"));
+    }
+
+    Ok(value)
+}
+
+/// Reads a single value from the start of `text`, returning it together with the remaining text
+pub(crate) fn read_luatext_prefix(text: &str) -> Result<(Value<'static>, &str)> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_trivia()?;
+    let value = parser.value()?;
+    parser.skip_trivia()?;
+
+    Ok((value, &text[parser.pos..]))
+}
+
+/// Nesting depth beyond which [`read_luatext`] rejects tables, instead of overflowing the stack.
+/// Savefiles stay well below it.
+pub const MAX_LUATEXT_DEPTH: usize = 256;
+
+struct Parser<'i> {
+    text: &'i [u8],
+    pos: usize,
+    /// Number of tables the parser is inside of
+    depth: usize,
+}
+
+impl<'i> Parser<'i> {
+    fn line(&self) -> usize {
+        self.text[..self.pos]
+            .iter()
+            .filter(|&&c| c == b'\n')
+            .count()
+            + 1
+    }
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::LuaText {
+            line: self.line(),
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+    fn rest(&self) -> &'i [u8] {
+        &self.text[self.pos..]
+    }
+    fn eat(&mut self, token: &str) -> bool {
+        let matches = self.rest().starts_with(token.as_bytes());
+        if matches {
+            self.pos += token.len();
+        }
+        matches
+    }
+    fn expect(&mut self, token: &str) -> Result<()> {
+        self.skip_trivia()?;
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{token}`"))),
+        }
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+                self.pos += 1;
+            }
+
+            if !self.eat("--") {
+                return Ok(());
+            }
+            if self.long_bracket_level().is_some() {
+                self.long_string()?;
+            } else {
+                while self.peek().is_some_and(|c| c != b'\n') {
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value<'static>> {
+        let Some(c) = self.peek() else {
+            return Err(self.error("expected value, found end of input"));
+        };
+        match c {
+            b'{' => self.table(),
+            b'"' | b'\'' => self.quoted_string().map(|s| Value::String(Cow::Owned(s))),
+            b'[' if self.long_bracket_level().is_some() => {
+                self.long_string().map(|s| Value::String(Cow::Owned(s)))
+            }
+            b'-' | b'.' | b'0'..=b'9' => self.number().map(Value::Number),
+            _ => match self.name() {
+                "nil" => Ok(Value::Nil),
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "math" if self.eat(".huge") => Ok(Value::Number(f64::INFINITY)),
+                "" => Err(self.error(format!("unexpected character `{}`", c as char))),
+                name => Err(self.error(format!("unexpected `{name}`"))),
+            },
+        }
+    }
+
+    fn name(&mut self) -> &'i str {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+        {
+            self.pos += 1;
+        }
+        // only ascii was consumed
+        std::str::from_utf8(&self.text[start..self.pos]).unwrap()
+    }
+
+    fn number(&mut self) -> Result<f64> {
+        if self.eat("0/0") {
+            return Ok(f64::NAN);
+        }
+        if self.eat("-math.huge") {
+            return Ok(f64::NEG_INFINITY);
+        }
+
+        let start = self.pos;
+        self.eat("-");
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'.')
+        {
+            let exponent = matches!(self.peek(), Some(b'e' | b'E'));
+            self.pos += 1;
+            if exponent && matches!(self.peek(), Some(b'-' | b'+')) {
+                self.pos += 1;
+            }
+        }
+
+        let text = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        let number = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok().map(|n| n as f64),
+            None => digits.parse::<f64>().ok(),
+        };
+        match number {
+            Some(number) if negative => Ok(-number),
+            Some(number) => Ok(number),
+            None => Err(self.error(format!("invalid number `{text}`"))),
+        }
+    }
+
+    fn quoted_string(&mut self) -> Result<String> {
+        let quote = self.text[self.pos];
+        self.pos += 1;
+
+        let mut bytes = Vec::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match c {
+                c if c == quote => break,
+                b'\n' => return Err(self.error("unterminated string")),
+                b'\\' => self.escape(&mut bytes)?,
+                c => bytes.push(c),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("string is not valid utf-8"))
+    }
+
+    fn escape(&mut self, bytes: &mut Vec<u8>) -> Result<()> {
+        let Some(c) = self.peek() else {
+            return Err(self.error("unterminated string"));
+        };
+        self.pos += 1;
+        match c {
+            b'a' => bytes.push(0x07),
+            b'b' => bytes.push(0x08),
+            b'f' => bytes.push(0x0c),
+            b'n' | b'\n' => bytes.push(b'\n'),
+            b'r' => bytes.push(b'\r'),
+            b't' => bytes.push(b'\t'),
+            b'v' => bytes.push(0x0b),
+            b'\\' | b'"' | b'\'' => bytes.push(c),
+            b'z' => {
+                while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+                    self.pos += 1;
+                }
+            }
+            b'x' => {
+                let hex = self.rest().get(..2).unwrap_or_default();
+                let byte = std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| self.error("invalid hex escape"))?;
+                self.pos += 2;
+                bytes.push(byte);
+            }
+            b'u' => {
+                let end = self.rest().iter().position(|&c| c == b'}');
+                let codepoint = end
+                    .and_then(|end| std::str::from_utf8(&self.rest()[..end]).ok())
+                    .and_then(|inner| inner.strip_prefix('{'))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid unicode escape"))?;
+                self.pos += end.unwrap() + 1;
+                bytes.extend_from_slice(codepoint.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            b'0'..=b'9' => {
+                let mut value = (c - b'0') as u32;
+                for _ in 0..2 {
+                    match self.peek() {
+                        Some(c @ b'0'..=b'9') => {
+                            value = value * 10 + (c - b'0') as u32;
+                            self.pos += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                let byte = u8::try_from(value).map_err(|_| self.error("escape too large"))?;
+                bytes.push(byte);
+            }
+            c => return Err(self.error(format!("invalid escape `\\{}`", c as char))),
+        }
+        Ok(())
+    }
+
+    /// Returns the level of a long bracket `[==[` at the current position
+    fn long_bracket_level(&self) -> Option<usize> {
+        let rest = self.rest().strip_prefix(b"[")?;
+        let level = rest.iter().take_while(|&&c| c == b'=').count();
+        (rest.get(level) == Some(&b'[')).then_some(level)
+    }
+
+    fn long_string(&mut self) -> Result<String> {
+        let level = self.long_bracket_level().unwrap();
+        self.pos += level + 2;
+        // a newline directly after the opening bracket is skipped
+        if !self.eat("\r\n") {
+            self.eat("\n");
+        }
+
+        let close = format!("]{}]", "=".repeat(level));
+        let start = self.pos;
+        let len = self
+            .rest()
+            .windows(close.len())
+            .position(|window| window == close.as_bytes())
+            .ok_or_else(|| self.error("unterminated long string"))?;
+        self.pos += len + close.len();
+
+        String::from_utf8(self.text[start..start + len].to_vec())
+            .map_err(|_| self.error("string is not valid utf-8"))
+    }
+
+    fn table(&mut self) -> Result<Value<'static>> {
+        if self.depth == MAX_LUATEXT_DEPTH {
+            return Err(Error::LuaTextTooDeep {
+                line: self.line(),
+                max_depth: MAX_LUATEXT_DEPTH,
+            });
+        }
+        self.depth += 1;
+        self.pos += 1;
+
+        let mut entries = Vec::new();
+        let mut next_index = 1.0;
+        loop {
+            self.skip_trivia()?;
+            if self.eat("}") {
+                break;
+            }
+
+            let (key, val) = if self.peek() == Some(b'[') && self.long_bracket_level().is_none() {
+                self.pos += 1;
+                self.skip_trivia()?;
+                let key = self.value()?;
+                self.expect("]")?;
+                self.expect("=")?;
+                self.skip_trivia()?;
+                (key, self.value()?)
+            } else {
+                let start = self.pos;
+                let name = self.name();
+                self.skip_trivia()?;
+                if !name.is_empty()
+                    && !name.starts_with(|c: char| c.is_ascii_digit())
+                    && self.eat("=")
+                    && self.peek() != Some(b'=')
+                {
+                    let key = Value::String(Cow::Owned(name.to_owned()));
+                    self.skip_trivia()?;
+                    (key, self.value()?)
+                } else {
+                    self.pos = start;
+                    let key = Value::Number(next_index);
+                    next_index += 1.0;
+                    (key, self.value()?)
+                }
+            };
+            entries.push((key, val));
+
+            self.skip_trivia()?;
+            if !self.eat(",") && !self.eat(";") {
+                self.expect("}")?;
+                break;
+            }
+        }
+
+        self.depth -= 1;
+        let mut table = LuaTable(entries);
+        table.sort();
+        Ok(Value::Table(table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::{MAX_LUATEXT_DEPTH, read_luatext, write_luatext};
+    use crate::parser::Error;
+    use crate::parser::luabins::{LuaTable, Value};
+
+    fn string(val: &str) -> Value<'static> {
+        Value::String(Cow::Owned(val.to_owned()))
+    }
+
+    #[test]
+    fn roundtrip_primitives() {
+        let mut table = LuaTable(vec![
+            (Value::Number(1.0), string("a \"quoted\"\n\\ string\u{1}")),
+            (Value::Number(2.5), Value::Number(-0.1)),
+            (Value::Bool(true), Value::Number(f64::INFINITY)),
+            (string("neg inf"), Value::Number(f64::NEG_INFINITY)),
+            (string("big"), Value::Number(1e300)),
+            (string("small"), Value::Number(-1e-300)),
+            (Value::Nil, Value::Bool(false)),
+            (string("empty"), Value::EMPTY_TABLE),
+        ]);
+        table.sort();
+        let value = Value::Table(table);
+
+        let mut text = String::new();
+        write_luatext(&mut text, &value);
+        assert_eq!(read_luatext(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn read_lua_syntax() {
+        let text = r#"
+            -- a comment
+            {
+                Name = 'single',
+                "first", 2;
+                [ [[long]] ] = [==[
+a ]] b]==],
+                --[[ block
+                comment ]]
+                nested = { x = 0x10, y = "\65\x42\u{43}", },
+            }
+        "#;
+        let value = read_luatext(text).unwrap();
+
+        let mut expected = LuaTable(vec![
+            (string("Name"), string("single")),
+            (Value::Number(1.0), string("first")),
+            (Value::Number(2.0), Value::Number(2.0)),
+            (string("long"), string("a ]] b")),
+            (
+                string("nested"),
+                Value::Table(LuaTable(vec![
+                    (string("x"), Value::Number(16.0)),
+                    (string("y"), string("ABC")),
+                ])),
+            ),
+        ]);
+        expected.sort();
+        assert_eq!(value, Value::Table(expected));
+    }
+
+    #[test]
+    fn read_errors() {
+        for text in [
+            "{",
+            "{ [1] 2 }",
+            "{ a = }",
+            "\"unterminated",
+            "{} {}",
+            "{ x = 1.2.3 }",
+        ] {
+            assert!(read_luatext(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn read_depth_limit() {
+        let nested = |depth| "{".repeat(depth) + &"}".repeat(depth);
+        assert!(read_luatext(&nested(MAX_LUATEXT_DEPTH)).is_ok());
+        assert!(matches!(
+            read_luatext(&nested(MAX_LUATEXT_DEPTH + 1)),
+            Err(Error::LuaTextTooDeep { line: 1, .. })
+        ));
+        assert!(matches!(
+            read_luatext(&"{".repeat(1_000_000)),
+            Err(Error::LuaTextTooDeep { .. })
+        ));
+    }
+}
@@ -1,4 +1,14 @@
+pub mod canonical;
+pub mod compact;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod intern;
 pub mod luabins;
+pub mod luatext;
+pub mod path;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod walk;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -21,6 +31,16 @@ pub enum Error {
 
     #[error("unexpected lua state")]
     Lua,
+    #[error("invalid lua text at line {line}: {message}")]
+    LuaText { line: usize, message: String },
+    #[error("lua text at line {line} nests tables more than {max_depth} levels deep")]
+    LuaTextTooDeep { line: usize, max_depth: usize },
+    #[error("invalid path `{0}`")]
+    InvalidPath(String),
+    #[error("expected a number")]
+    NotANumber,
+    #[error("cannot replace an integer with the non-integral number {0}")]
+    NotAnInteger(f64),
 }
 
 pub fn read_bytes_array<const N: usize>(data: &mut &[u8]) -> Result<[u8; N]> {
@@ -0,0 +1,192 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use super::luabins::Value;
+use super::{Error, Result};
+
+/// Path to a node in a lua state, made up of the keys leading to it.
+///
+/// Unlike the position of an entry, the keys stay the same when a table is sorted or entries are inserted.
+/// Displayed and parsed like `GameState.Resources["Key With Dots"][3]`.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LuaPath<'a>(Vec<Value<'a>>);
+
+impl<'a> LuaPath<'a> {
+    pub fn new() -> Self {
+        LuaPath(Vec::new())
+    }
+
+    pub fn segments(&self) -> &[Value<'a>] {
+        &self.0
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(&mut self, key: Value<'a>) {
+        self.0.push(key);
+    }
+    pub fn pop(&mut self) -> Option<Value<'a>> {
+        self.0.pop()
+    }
+    #[must_use]
+    pub fn join(&self, key: Value<'a>) -> LuaPath<'a> {
+        let mut path = self.clone();
+        path.push(key);
+        path
+    }
+    pub fn parent(&self) -> Option<LuaPath<'a>> {
+        let (_, parent) = self.0.split_last()?;
+        Some(LuaPath(parent.to_vec()))
+    }
+
+    pub fn into_owned(self) -> LuaPath<'static> {
+        LuaPath(self.0.into_iter().map(Value::into_owned).collect())
+    }
+
+    /// Returns the paths of all ancestors, starting from the root
+    pub fn ancestors(&self) -> impl DoubleEndedIterator<Item = LuaPath<'a>> + '_ {
+        (0..self.len()).map(|i| LuaPath(self.0[..i].to_vec()))
+    }
+    pub fn starts_with(&self, prefix: &LuaPath) -> bool {
+        self.0.len() >= prefix.0.len() && self.0.iter().zip(&prefix.0).all(|(a, b)| a == b)
+    }
+
+    pub fn resolve<'v, 'l>(&self, value: &'v Value<'l>) -> Option<&'v Value<'l>> {
+        self.0
+            .iter()
+            .try_fold(value, |acc, key| acc.as_table()?.find(key))
+    }
+    pub fn resolve_mut<'v, 'l>(&self, value: &'v mut Value<'l>) -> Option<&'v mut Value<'l>> {
+        self.0
+            .iter()
+            .try_fold(value, |acc, key| acc.as_table_mut()?.find_mut(key))
+    }
+}
+
+impl FromStr for LuaPath<'static> {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPath(text.to_owned());
+
+        let mut segments = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            if let Some(bracketed) = rest.strip_prefix('[') {
+                let (key, after) =
+                    super::luatext::read_luatext_prefix(bracketed).map_err(|_| invalid())?;
+                if !key.is_primitive() {
+                    return Err(invalid());
+                }
+                rest = after.strip_prefix(']').ok_or_else(invalid)?;
+                segments.push(key);
+            } else {
+                let name = match segments.is_empty() {
+                    true => rest,
+                    false => rest.strip_prefix('.').ok_or_else(invalid)?,
+                };
+                let end = name.find(['.', '[']).unwrap_or(name.len());
+                if !is_identifier(&name[..end]) {
+                    return Err(invalid());
+                }
+                segments.push(Value::String(Cow::Owned(name[..end].to_owned())));
+                rest = &name[end..];
+            }
+        }
+
+        Ok(LuaPath(segments))
+    }
+}
+
+impl<'a> FromIterator<Value<'a>> for LuaPath<'a> {
+    fn from_iter<T: IntoIterator<Item = Value<'a>>>(iter: T) -> Self {
+        LuaPath(iter.into_iter().collect())
+    }
+}
+
+fn is_identifier(str: &str) -> bool {
+    str.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && str.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl fmt::Display for LuaPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                Value::String(key) if is_identifier(key) => {
+                    if i != 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(key)?;
+                }
+                key => {
+                    let mut text = String::new();
+                    super::luatext::write_luatext(&mut text, key);
+                    write!(f, "[{text}]")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LuaPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LuaPath({self})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LuaPath;
+    use crate::parser::luatext::read_luatext;
+
+    #[test]
+    fn parse_display_roundtrip() {
+        for text in [
+            "",
+            "GameState",
+            "GameState.Resources.CardUpgradePoints",
+            r#"GameState.Resources["Key With Dots.x"][3]"#,
+            r#"["a\"b"].c[true][-1.5]"#,
+            "[1][2]",
+            "_a1._b2",
+        ] {
+            let path: LuaPath = text.parse().unwrap();
+            assert_eq!(path.to_string(), text);
+        }
+
+        for text in [".a", "a.", "a..b", "a[1", "a[{}]", "1a", "a b", "a[1]b"] {
+            assert!(text.parse::<LuaPath>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let mut value = read_luatext(r#"{ a = { ["b.c"] = { 10, 20 } } }"#).unwrap();
+
+        let path: LuaPath = r#"a["b.c"][2]"#.parse().unwrap();
+        assert_eq!(path.resolve(&value).and_then(|v| v.as_number()), Some(20.0));
+        *path
+            .resolve_mut(&mut value)
+            .unwrap()
+            .as_number_mut()
+            .unwrap() = 30.0;
+        assert_eq!(
+            value.get_path(r#"a["b.c"][2]"#).and_then(|v| v.as_number()),
+            Some(30.0)
+        );
+
+        assert!(value.get_path("a.b").is_none());
+        assert!(value.get_path(r#"a["b.c"][2].x"#).is_none());
+
+        let ancestors: Vec<_> = path.ancestors().map(|p| p.to_string()).collect();
+        assert_eq!(ancestors, ["", "a", r#"a["b.c"]"#]);
+        assert!(path.starts_with(&"a".parse().unwrap()));
+    }
+}
@@ -0,0 +1,418 @@
+//! Writing lua values with serde, the inverse of [`super::de`].
+//!
+//! Sequences become tables with keys `1..=n`, structs and maps become tables keyed by their field names or keys.
+//! `None` and `()` are `nil`, and are omitted when they appear as table values, since lua tables can't store `nil`.
+//! Enums are externally tagged, i.e. unit variants are strings and other variants are tables with a single entry.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use super::de::Error;
+use super::luabins::{LuaTable, MAX_EXACT_INTEGER, Value};
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        <Error as serde::de::Error>::custom(msg)
+    }
+}
+
+/// Serializes a `T` into a lua value.
+pub fn to_lua<T: Serialize + ?Sized>(value: &T) -> Result<Value<'static>, Error> {
+    value.serialize(Serializer)
+}
+
+pub struct Serializer;
+
+fn string(val: impl Into<String>) -> Value<'static> {
+    Value::String(Cow::Owned(val.into()))
+}
+
+fn integer(val: impl TryInto<i64> + fmt::Display + Copy) -> Result<Value<'static>, Error> {
+    match val.try_into() {
+        Ok(int) if int.unsigned_abs() <= MAX_EXACT_INTEGER as u64 => Ok(Value::Number(int as f64)),
+        _ => Err(ser::Error::custom(format_args!(
+            "{val} cannot be represented exactly as a lua number"
+        ))),
+    }
+}
+
+fn single_entry(key: &'static str, value: Value<'static>) -> Value<'static> {
+    Value::Table(LuaTable(vec![(string(key), value)]))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeVariant<SerializeSeq>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+        Ok(Value::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+        integer(v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+        Ok(Value::Number(v as f64))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+        Ok(Value::Number(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        Ok(string(v))
+    }
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(string(v))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+        v.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(Value::Nil)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(Value::Nil)
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Error> {
+        Ok(Value::Nil)
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        Ok(string(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        Ok(single_entry(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(SerializeSeq {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            len: 0,
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(SerializeMap {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            next_key: None,
+        })
+    }
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+fn finish_table(entries: Vec<(Value<'static>, Value<'static>)>) -> Value<'static> {
+    let mut table = LuaTable(entries);
+    table.sort();
+    Value::Table(table)
+}
+
+pub struct SerializeSeq {
+    entries: Vec<(Value<'static>, Value<'static>)>,
+    len: usize,
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.len += 1;
+        let value = value.serialize(Serializer)?;
+        if value != Value::Nil {
+            self.entries.push((Value::Number(self.len as f64), value));
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(finish_table(self.entries))
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeMap {
+    entries: Vec<(Value<'static>, Value<'static>)>,
+    next_key: Option<Value<'static>>,
+}
+
+impl SerializeMap {
+    fn insert(&mut self, key: Value<'static>, value: Value<'static>) -> Result<(), Error> {
+        if key == Value::Nil {
+            return Err(ser::Error::custom("table keys cannot be nil"));
+        }
+        if value != Value::Nil {
+            self.entries.push((key, value));
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.next_key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| ser::Error::custom("serialize_value called before serialize_key"))?;
+        let value = value.serialize(Serializer)?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(finish_table(self.entries))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = value.serialize(Serializer)?;
+        self.insert(string(key), value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+pub struct SerializeVariant<T> {
+    variant: &'static str,
+    inner: T,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeSeq> {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        let inner = ser::SerializeSeq::end(self.inner)?;
+        Ok(single_entry(self.variant, inner))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+    fn end(self) -> Result<Self::Ok, Error> {
+        let inner = ser::SerializeMap::end(self.inner)?;
+        Ok(single_entry(self.variant, inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::to_lua;
+    use crate::parser::de::from_lua;
+    use crate::parser::luabins::{MAX_EXACT_INTEGER, Value};
+    use crate::parser::luatext::read_luatext;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Run {
+        weapon: String,
+        depth: u32,
+        boons: Vec<String>,
+        keepsake: Option<String>,
+        counts: BTreeMap<String, f64>,
+        kind: Kind,
+        shape: Shape,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Kind {
+        Cleared,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Point(f64, f64),
+        Named { name: String },
+    }
+
+    #[test]
+    fn serialize_struct() {
+        let run = Run {
+            weapon: "WeaponStaffSwing".to_owned(),
+            depth: 12,
+            boons: vec!["ZeusWeaponBoon".to_owned(), "HeraSprintBoon".to_owned()],
+            keepsake: None,
+            counts: BTreeMap::from([("x".to_owned(), 0.5)]),
+            kind: Kind::Cleared,
+            shape: Shape::Point(1.0, 2.0),
+        };
+        let value = to_lua(&run).unwrap();
+
+        let expected = read_luatext(
+            r#"{
+                weapon = "WeaponStaffSwing",
+                depth = 12,
+                boons = { "ZeusWeaponBoon", "HeraSprintBoon" },
+                counts = { x = 0.5 },
+                kind = "Cleared",
+                shape = { Point = { 1, 2 } },
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+
+        assert_eq!(from_lua::<Run>(&value).unwrap(), run);
+
+        let named = to_lua(&Shape::Named {
+            name: "a".to_owned(),
+        })
+        .unwrap();
+        assert_eq!(
+            named,
+            read_luatext(r#"{ Named = { name = "a" } }"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_sparse_sequence() {
+        let value = to_lua(&[Some(1), None, Some(3)]).unwrap();
+        assert_eq!(value, read_luatext("{ [1] = 1, [3] = 3 }").unwrap());
+
+        assert!(to_lua(&u64::MAX).is_err());
+        assert!(to_lua(&i64::MIN).is_err());
+        assert!(to_lua(&i64::MAX).is_err());
+        assert_eq!(
+            to_lua(&-MAX_EXACT_INTEGER).unwrap(),
+            Value::Number(-MAX_EXACT_INTEGER as f64)
+        );
+    }
+}
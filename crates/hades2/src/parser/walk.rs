@@ -0,0 +1,142 @@
+use std::ops::ControlFlow;
+
+use super::luabins::Value;
+use super::path::LuaPath;
+
+/// Returned from the callbacks of [`Value::walk`] and [`Value::walk_mut`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walk {
+    Continue,
+    /// Don't descend into the children of the current value
+    Prune,
+    /// Stop the walk entirely
+    Stop,
+}
+
+impl<'l> Value<'l> {
+    /// Visits `self` and every value below it in depth-first pre-order, together with its path relative to `self`.
+    ///
+    /// Unlike [`Value::visit`], keys are not visited on their own, they only appear in the paths.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&LuaPath<'a>, &'a Value<'l>) -> Walk) {
+        let _ = walk_inner(self, &mut LuaPath::new(), f);
+    }
+
+    /// Like [`Value::walk`], but with mutable access.
+    ///
+    /// Since a table is visited before its children, the callback can add, remove or rename
+    /// its entries, and the walk continues with the updated entries.
+    pub fn walk_mut(&mut self, f: &mut impl FnMut(&LuaPath<'l>, &mut Value<'l>) -> Walk) {
+        let _ = walk_mut_inner(self, &mut LuaPath::new(), f);
+    }
+}
+
+fn walk_inner<'a, 'l>(
+    value: &'a Value<'l>,
+    path: &mut LuaPath<'a>,
+    f: &mut impl FnMut(&LuaPath<'a>, &'a Value<'l>) -> Walk,
+) -> ControlFlow<()> {
+    match f(path, value) {
+        Walk::Continue => {}
+        Walk::Prune => return ControlFlow::Continue(()),
+        Walk::Stop => return ControlFlow::Break(()),
+    }
+
+    if let Value::Table(table) = value {
+        for (key, val) in table.iter() {
+            path.push(key.borrowed());
+            walk_inner(val, path, f)?;
+            path.pop();
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn walk_mut_inner<'l>(
+    value: &mut Value<'l>,
+    path: &mut LuaPath<'l>,
+    f: &mut impl FnMut(&LuaPath<'l>, &mut Value<'l>) -> Walk,
+) -> ControlFlow<()> {
+    match f(path, value) {
+        Walk::Continue => {}
+        Walk::Prune => return ControlFlow::Continue(()),
+        Walk::Stop => return ControlFlow::Break(()),
+    }
+
+    if let Value::Table(table) = value {
+        for (key, val) in table.iter_mut() {
+            path.push(key.clone());
+            walk_mut_inner(val, path, f)?;
+            path.pop();
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Walk;
+    use crate::parser::luabins::Value;
+    use crate::parser::luatext::read_luatext;
+
+    #[test]
+    fn walk_prune_stop() {
+        let value = read_luatext(r#"{ a = { b = 1, c = { d = 2 } }, e = { 3 } }"#).unwrap();
+
+        let mut paths = Vec::new();
+        value.walk(&mut |path, _| {
+            paths.push(path.to_string());
+            Walk::Continue
+        });
+        assert_eq!(paths, ["", "a", "a.b", "a.c", "a.c.d", "e", "e[1]"]);
+
+        let mut paths = Vec::new();
+        value.walk(&mut |path, val| {
+            paths.push(path.to_string());
+            match val.get("c") {
+                Some(_) => Walk::Prune,
+                None => Walk::Continue,
+            }
+        });
+        assert_eq!(paths, ["", "a", "e", "e[1]"]);
+
+        let mut paths = Vec::new();
+        value.walk(&mut |path, _| {
+            paths.push(path.to_string());
+            match path.len() {
+                2 => Walk::Stop,
+                _ => Walk::Continue,
+            }
+        });
+        assert_eq!(paths, ["", "a", "a.b"]);
+    }
+
+    #[test]
+    fn walk_mut_transform() {
+        let mut value =
+            read_luatext(r#"{ a = { b = 1, c = { d = 2 } }, e = { 3, "x" } }"#).unwrap();
+
+        value.walk_mut(&mut |_, val| {
+            if let Some(table) = val.as_table_mut() {
+                table.0.retain(|(_, val)| !val.is_str("x"));
+            }
+            if let Some(number) = val.as_number_mut() {
+                *number *= 10.0;
+            }
+            Walk::Continue
+        });
+
+        let expected = read_luatext(r#"{ a = { b = 10, c = { d = 20 } }, e = { 30 } }"#).unwrap();
+        assert_eq!(value, expected);
+
+        let mut paths = Vec::new();
+        value.walk_mut(&mut |path, val: &mut Value| {
+            if val.is_primitive() {
+                paths.push(path.to_string());
+            }
+            Walk::Continue
+        });
+        assert_eq!(paths, ["a.b", "a.c.d", "e[1]"]);
+    }
+}
@@ -1,47 +1,20 @@
-use crate::LocateError;
-use std::path::{Path, PathBuf};
+#[cfg(feature = "serde")]
+pub mod tagged;
 
 const MAGIC: [u8; 4] = [0x53, 0x47, 0x42, 0x31];
 const LZ4_MIN_DECOPMRESS_LEN: usize = 15679488;
 
-#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
-pub(crate) fn save_dir(_steam_dir: &Path) -> Result<PathBuf, LocateError> {
-    Err(LocateError::UnsupportedPlatform)
-}
-
-#[cfg(target_os = "windows")]
-pub(crate) fn save_dir(_steam_dir: &Path) -> Result<PathBuf, LocateError> {
-    let home_dir = std::env::home_dir().ok_or_else(|| LocateError::Other)?;
-    let dir = home_dir.join("Saved Games/Hades II");
-    if !dir.exists() {
-        return Err(LocateError::NotFound("`~/Saved Games/Hades II`"));
-    }
-    Ok(dir)
-}
-
-#[cfg(target_os = "macos")]
-pub(crate) fn save_dir(_steam_dir: &Path) -> Result<PathBuf, LocateError> {
-    Err(LocateError::NotFound(
-        "macOS not supported yet. Please open an issue on github. save dir",
-    ))
-}
-
-#[cfg(target_os = "linux")]
-pub(crate) fn save_dir(steam_dir: &Path) -> Result<PathBuf, LocateError> {
-    let dir = steam_dir
-        .join("steamapps/compatdata/1145350/pfx/drive_c/users/steamuser/Saved Games/Hades II");
-    if !dir.exists() {
-        return Err(LocateError::NotFound("steam game 1145350"));
-    }
-
-    Ok(dir)
-}
-
 pub use crate::parser::Result;
-pub use crate::parser::luabins::{LuaTable, Value as LuaValue};
+pub use crate::parser::compact::{CompactState, NodeRef};
+pub use crate::parser::intern::{Interner, StringArena};
+pub use crate::parser::luabins::{LuaTable, MAX_EXACT_INTEGER, Value as LuaValue};
+pub use crate::parser::luatext::{MAX_LUATEXT_DEPTH, read_luatext, write_luatext};
+pub use crate::parser::path::LuaPath;
+pub use crate::parser::walk::Walk;
 use crate::parser::*;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Savefile {
     pub version: u16,
     pub version_unk: u16, // on this file https://github.com/jakobhellermann/hades2-tools/issues/1 the second u16 has value 1
@@ -62,7 +35,7 @@ pub struct Savefile {
 
 impl Savefile {
     pub fn parse(mut data: &[u8]) -> Result<(Savefile, LuaValue<'static>)> {
-        let computed_checksum = adler32::RollingAdler32::from_buffer(&data[8..]).hash();
+        let computed_checksum = computed_checksum(data)?;
 
         let (savefile, lua_state) = parse_inner(&mut data)?;
         let lua_state = lz4_flex::block::decompress(lua_state, LZ4_MIN_DECOPMRESS_LEN)?;
@@ -80,12 +53,53 @@ impl Savefile {
         Ok((savefile, lua_state))
     }
 
+    /// Like [`Savefile::parse`], but all strings of the lua state are borrowed from `interner`
+    pub fn parse_interned<'i>(
+        mut data: &[u8],
+        interner: &Interner<'i>,
+    ) -> Result<(Savefile, LuaValue<'i>)> {
+        let computed_checksum = computed_checksum(data)?;
+
+        let (savefile, lua_state) = parse_inner(&mut data)?;
+        if computed_checksum != savefile.checksum {
+            return Err(Error::Checksum);
+        }
+
+        let lua_state = lz4_flex::block::decompress(lua_state, LZ4_MIN_DECOPMRESS_LEN)?;
+        let lua_state = interner.read_luabins(&mut lua_state.as_slice())?;
+        let [lua_state] = <[_; 1]>::try_from(lua_state).map_err(|_| Error::Lua)?;
+
+        Ok((savefile, lua_state))
+    }
+
+    /// Like [`Savefile::parse`], but reads the lua state into a [`CompactState`]
+    pub fn parse_compact(mut data: &[u8]) -> Result<(Savefile, CompactState)> {
+        let computed_checksum = computed_checksum(data)?;
+
+        let (savefile, lua_state) = parse_inner(&mut data)?;
+        if computed_checksum != savefile.checksum {
+            return Err(Error::Checksum);
+        }
+
+        let lua_state = lz4_flex::block::decompress(lua_state, LZ4_MIN_DECOPMRESS_LEN)?;
+        let lua_state = CompactState::read_luabins(&lua_state)?;
+
+        Ok((savefile, lua_state))
+    }
+
     pub fn parse_header_only(mut data: &[u8]) -> Result<Savefile> {
         let (savefile, _) = parse_inner(&mut data)?;
         Ok(savefile)
     }
 }
 
+/// Checksum over everything after the signature and the stored checksum
+fn computed_checksum(data: &[u8]) -> Result<u32> {
+    let checksummed = data.get(8..).ok_or(Error::Eof)?;
+    Ok(adler32::RollingAdler32::from_buffer(checksummed).hash())
+}
+
+#[cfg_attr(not(feature = "install"), allow(unused))]
 pub(crate) fn parse_active_profile<'i>(data: &mut &'i [u8]) -> Result<&'i str> {
     let signature = read_bytes_array::<4>(data)?;
     if signature != MAGIC {
@@ -175,6 +189,25 @@ impl Savefile {
         let compressed = lz4_flex::compress(&lua_state_bytes);
         serialize_inner(out, self, &compressed)
     }
+
+    /// Stable hash of the header and lua state, ignoring the `checksum` and `timestamp`.
+    ///
+    /// Two saves with the same content hash hold the same progress, even if the order of their
+    /// table entries differs. See [`LuaValue::fingerprint`].
+    pub fn content_hash(&self, lua_state: &LuaValue<'_>) -> u64 {
+        let header = Savefile {
+            checksum: 0,
+            timestamp: 0,
+            ..self.clone()
+        };
+        let mut header_bytes = Vec::new();
+        serialize_inner(&mut header_bytes, &header, &[]).expect("writing to a vec cannot fail");
+
+        let mut hasher = canonical::Fnv::new();
+        hasher.write(&header_bytes);
+        hasher.write_u64(lua_state.fingerprint());
+        hasher.finish()
+    }
 }
 
 fn serialize_inner<W: std::io::Write>(
@@ -259,9 +292,37 @@ mod test {
     fn roundtrip_reparse_savefile_18() -> Result<()> {
         roundtrip_reparse_savefile(TEST_PROFILE_V18)
     }
+    #[test]
+    fn roundtrip_luatext_18() -> Result<()> {
+        roundtrip_luatext(TEST_PROFILE_V18)
+    }
 
     const TEST_REGRESSION_SPLITVERSION: &[u8] =
         include_bytes!("../../../../testdata/regression/1.sav").as_slice();
+    #[test]
+    fn content_hash_18() -> Result<()> {
+        let (savefile, lua_state) = super::Savefile::parse(TEST_PROFILE_V18)?;
+        let hash = savefile.content_hash(&lua_state);
+
+        let mut resaved = savefile.clone();
+        resaved.timestamp += 60;
+        resaved.checksum = 0;
+        let mut reordered = lua_state.clone();
+        reordered.as_table_mut().unwrap().0.reverse();
+        assert_eq!(resaved.content_hash(&reordered), hash);
+
+        resaved.runs += 1;
+        assert_ne!(resaved.content_hash(&lua_state), hash);
+
+        let mut changed = lua_state.clone();
+        *changed
+            .get_path_mut("GameState.Resources.CardUpgradePoints")
+            .unwrap() = super::LuaValue::Number(100.0);
+        assert_ne!(savefile.content_hash(&changed), hash);
+
+        Ok(())
+    }
+
     #[test]
     fn roundtrip_savefile_splitversion() -> Result<()> {
         roundtrip_reparse_savefile(TEST_REGRESSION_SPLITVERSION)
@@ -289,6 +350,17 @@ mod test {
         Ok(())
     }
 
+    fn roundtrip_luatext(data: &[u8]) -> Result<()> {
+        let (_, lua_state) = super::Savefile::parse(data)?;
+
+        let mut text = String::new();
+        super::write_luatext(&mut text, &lua_state);
+        let reparsed = super::read_luatext(&text)?;
+        assert_eq!(lua_state, reparsed);
+
+        Ok(())
+    }
+
     fn roundtrip_savefile(data: &[u8]) -> Result<()> {
         let (savefile, lua_state_compressed) = super::parse_inner(&mut &*data)?;
 
@@ -301,7 +373,7 @@ mod test {
     }
 
     fn roundtrip_reparse_savefile(data: &[u8]) -> Result<()> {
-        let (mut savefile, lua_state) = super::Savefile::parse(&mut &*data)?;
+        let (mut savefile, lua_state) = super::Savefile::parse(data)?;
 
         let mut out = Vec::new();
         savefile.serialize(&mut out, &lua_state)?;
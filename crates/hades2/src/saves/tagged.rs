@@ -0,0 +1,247 @@
+//! Lossless serde representation of lua states.
+//!
+//! The plain [`Serialize`] implementation of [`LuaValue`] is convenient for viewing, but merges
+//! number and string keys and can't represent non-finite numbers. [`Tagged`] instead uses
+//! - `null`, `true`/`false`, numbers and strings for the respective primitives, with integral numbers written without a fraction
+//! - `{ "$number": "inf" | "-inf" | "nan" }` for non-finite numbers
+//! - objects for tables, with entries in table order and keys tagged by their type:
+//!   `"s:Name"` for strings, `"n:1"` for numbers, `"b:true"` for booleans and `"nil"` for nil.
+//!
+//! Tables can't be used as keys, which never happens in savefiles.
+//! When using `serde_json`, its `float_roundtrip` feature is required for numbers to be parsed exactly.
+//!
+//! [`TaggedSavefile`] additionally includes the [`Savefile`] header, so that it can be turned back into a `.sav`:
+//! ```json
+//! { "savefile": { "version": 18, ... }, "lua_state": { "s:GameState": { ... } } }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+use super::{LuaTable, LuaValue, Savefile};
+use crate::parser::luabins::serialize_number;
+
+const NUMBER_TAG: &str = "$number";
+
+/// Wrapper serializing a [`LuaValue`] in the tagged representation, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tagged<T>(pub T);
+
+/// A [`Savefile`] header and its lua state, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TaggedSavefile {
+    pub savefile: Savefile,
+    pub lua_state: Tagged<LuaValue<'static>>,
+}
+
+fn tag_key(key: &LuaValue) -> Option<String> {
+    Some(match key {
+        LuaValue::Nil => "nil".to_owned(),
+        LuaValue::Bool(val) => format!("b:{val}"),
+        LuaValue::Number(val) => format!("n:{val}"),
+        LuaValue::String(val) => format!("s:{val}"),
+        LuaValue::Table(_) => return None,
+    })
+}
+
+fn untag_key(key: &str) -> Option<LuaValue<'static>> {
+    if key == "nil" {
+        return Some(LuaValue::Nil);
+    }
+    let (tag, rest) = key.split_once(':')?;
+    Some(match tag {
+        "s" => LuaValue::String(Cow::Owned(rest.to_owned())),
+        "n" => LuaValue::Number(rest.parse().ok()?),
+        "b" => LuaValue::Bool(rest.parse().ok()?),
+        _ => return None,
+    })
+}
+
+impl Serialize for Tagged<LuaValue<'_>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Tagged(&self.0).serialize(serializer)
+    }
+}
+
+impl Serialize for Tagged<&LuaValue<'_>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self.0 {
+            LuaValue::Nil => serializer.serialize_unit(),
+            LuaValue::Bool(val) => serializer.serialize_bool(val),
+            LuaValue::Number(val) if val.is_finite() => serialize_number(val, serializer),
+            LuaValue::Number(val) => {
+                let name = match val {
+                    f64::INFINITY => "inf",
+                    f64::NEG_INFINITY => "-inf",
+                    _ => "nan",
+                };
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(NUMBER_TAG, name)?;
+                map.end()
+            }
+            LuaValue::String(ref val) => serializer.serialize_str(val),
+            LuaValue::Table(ref table) => {
+                let mut map = serializer.serialize_map(Some(table.len()))?;
+                for (key, val) in table {
+                    let key = tag_key(key)
+                        .ok_or_else(|| ser::Error::custom("tables cannot be used as keys"))?;
+                    map.serialize_entry(&key, &Tagged(val))?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Tagged<LuaValue<'static>> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TaggedVisitor).map(Tagged)
+    }
+}
+
+struct TaggedVisitor;
+
+impl<'de> Visitor<'de> for TaggedVisitor {
+    type Value = LuaValue<'static>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tagged lua value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(LuaValue::Nil)
+    }
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(LuaValue::Nil)
+    }
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(LuaValue::Bool(v))
+    }
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(LuaValue::Number(v as f64))
+    }
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(LuaValue::Number(v as f64))
+    }
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(LuaValue::Number(v))
+    }
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(LuaValue::String(Cow::Owned(v.to_owned())))
+    }
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(LuaValue::String(Cow::Owned(v)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, _: A) -> Result<Self::Value, A::Error> {
+        Err(de::Error::invalid_type(de::Unexpected::Seq, &self))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key::<Cow<str>>()? {
+            if key == NUMBER_TAG && entries.is_empty() {
+                let number = match map.next_value::<Cow<str>>()?.as_ref() {
+                    "inf" => f64::INFINITY,
+                    "-inf" => f64::NEG_INFINITY,
+                    "nan" => f64::NAN,
+                    other => {
+                        return Err(de::Error::invalid_value(
+                            de::Unexpected::Str(other),
+                            &"`inf`, `-inf` or `nan`",
+                        ));
+                    }
+                };
+                return Ok(LuaValue::Number(number));
+            }
+
+            let key = untag_key(&key).ok_or_else(|| {
+                de::Error::invalid_value(de::Unexpected::Str(&key), &"a tagged key like `s:Name`")
+            })?;
+            let Tagged(value) = map.next_value()?;
+            entries.push((key, value));
+        }
+
+        Ok(LuaValue::Table(LuaTable(entries)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    use super::{Tagged, TaggedSavefile};
+    use crate::saves::{LuaValue, Savefile, read_luatext};
+
+    const TEST_PROFILE_V17: &[u8] =
+        include_bytes!("../../../../testdata/Profile.v17.sav").as_slice();
+    const TEST_PROFILE_V18: &[u8] =
+        include_bytes!("../../../../testdata/Profile.v18.sav").as_slice();
+
+    #[test]
+    fn roundtrip_tagged_17() -> Result<()> {
+        roundtrip_tagged(TEST_PROFILE_V17)
+    }
+    #[test]
+    fn roundtrip_tagged_18() -> Result<()> {
+        roundtrip_tagged(TEST_PROFILE_V18)
+    }
+
+    fn roundtrip_tagged(data: &[u8]) -> Result<()> {
+        let (savefile, lua_state) = Savefile::parse(data)?;
+        let tagged = TaggedSavefile {
+            savefile,
+            lua_state: Tagged(lua_state),
+        };
+
+        let json = serde_json::to_string(&tagged)?;
+        let reparsed: TaggedSavefile = serde_json::from_str(&json)?;
+        assert_eq!(tagged, reparsed);
+
+        let mut out = Vec::new();
+        reparsed
+            .savefile
+            .serialize(&mut out, &reparsed.lua_state.0)?;
+        let (_, lua_state) = Savefile::parse(&out)?;
+        assert_eq!(lua_state, tagged.lua_state.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tagged_keys_and_numbers() -> Result<()> {
+        let value = read_luatext(
+            r#"{ [1] = "1", ["1"] = 1, [true] = 0/0, [nil] = math.huge, [2.5] = -math.huge, ["x"] = 3.0 }"#,
+        )?;
+
+        let json = serde_json::to_string(&Tagged(&value))?;
+        assert!(json.contains(r#""n:1":"1""#), "{json}");
+        assert!(json.contains(r#""s:1":1"#), "{json}");
+        assert!(!json.contains(".0"), "{json}");
+        assert!(json.contains(r#""nil":{"$number":"inf"}"#), "{json}");
+
+        let Tagged(reparsed) = serde_json::from_str::<Tagged<LuaValue>>(&json)?;
+        let LuaValue::Table(reparsed) = reparsed else {
+            panic!()
+        };
+        let LuaValue::Table(value) = value else {
+            panic!()
+        };
+        assert_eq!(value.len(), reparsed.len());
+        for ((key_a, val_a), (key_b, val_b)) in value.iter().zip(reparsed.iter()) {
+            assert_eq!(key_a, key_b);
+            match (val_a, val_b) {
+                (LuaValue::Number(a), LuaValue::Number(b)) => {
+                    assert_eq!(a.to_bits(), b.to_bits())
+                }
+                _ => assert_eq!(val_a, val_b),
+            }
+        }
+
+        Ok(())
+    }
+}
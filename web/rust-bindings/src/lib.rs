@@ -1,4 +1,6 @@
 use hades2::Result;
+use hades2::saves::Savefile;
+use hades2::saves::tagged::{Tagged, TaggedSavefile};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -11,14 +13,32 @@ extern "C" {
 
 #[wasm_bindgen]
 pub fn expand_savefile(data: &[u8], format: &str) -> Result<String, JsError> {
-    let (_savefile, lua_state) = hades2::saves::Savefile::parse(data)?;
+    if format == "json-lossless" {
+        let (savefile, lua_state) = Savefile::parse(data)?;
+        return Ok(serde_json::to_string(&TaggedSavefile {
+            savefile,
+            lua_state: Tagged(lua_state),
+        })?);
+    }
 
+    // the compact representation keeps memory usage low for large profiles
+    let (_, lua_state) = Savefile::parse_compact(data)?;
     let text = match format {
         "text" => format!("{:#?}", lua_state),
         "json" => serde_json::to_string(&lua_state)?,
         "json-pretty" => serde_json::to_string_pretty(&lua_state)?,
-        _ => return Err(JsError::new("expected `json` or `text`")),
+        _ => return Err(JsError::new("expected `json`, `json-lossless` or `text`")),
     };
 
     Ok(text)
 }
+
+/// Turns the output of `expand_savefile(_, "json-lossless")` back into a savefile
+#[wasm_bindgen]
+pub fn encode_savefile(json: &str) -> Result<Vec<u8>, JsError> {
+    let tagged: TaggedSavefile = serde_json::from_str(json)?;
+
+    let mut out = Vec::new();
+    tagged.savefile.serialize(&mut out, &tagged.lua_state.0)?;
+    Ok(out)
+}
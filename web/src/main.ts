@@ -7,6 +7,8 @@ let errorText = document.getElementById("error") as HTMLParagraphElement;
 
 let downloadTextBtn = document.getElementById("download-text") as HTMLButtonElement;
 let downloadJsonBtn = document.getElementById("download-json") as HTMLButtonElement;
+let downloadJsonLosslessBtn = document.getElementById("download-json-lossless") as HTMLButtonElement;
+let jsonselect = document.getElementById("jsonselect") as HTMLInputElement;
 let loadJsonBtn = document.getElementById("load-json") as HTMLButtonElement;
 
 let viewer = document.getElementById("viewer")!;
@@ -15,10 +17,12 @@ let viewerControlExpand = document.getElementById("viewer-control-expand")!;
 
 downloadTextBtn.addEventListener("click", () => data && processSavefile(data, "text"));
 downloadJsonBtn.addEventListener("click", () => data && processSavefile(data, "json-pretty"));
+downloadJsonLosslessBtn.addEventListener("click", () => data && processSavefile(data, "json-lossless"));
+jsonselect.addEventListener("change", onJsonselectChange);
 loadJsonBtn.addEventListener("click", () => data && loadJson(data));
 viewerControlExpand.addEventListener("click", toggleExpandAll);
 
-type Format = "json" | "json-pretty" | "text";
+type Format = "json" | "json-pretty" | "json-lossless" | "text";
 
 fileselect.addEventListener("change", onFileselectChange);
 onFileselectChange();
@@ -30,6 +34,7 @@ async function onFileselectChange() {
   let set = fileselect.files?.[0] != null;
   downloadTextBtn.disabled = !set;
   downloadJsonBtn.disabled = !set;
+  downloadJsonLosslessBtn.disabled = !set;
   loadJsonBtn.disabled = !set;
 
   let file = fileselect.files?.[0];
@@ -47,16 +52,44 @@ function loadFile(file: File): Promise<Uint8Array> {
   });
 }
 
+function errorMessage(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  else if (typeof error == "string") return error;
+  else return "" + error;
+}
+
+async function onJsonselectChange() {
+  errorText.innerText = "";
+  let file = jsonselect.files?.[0];
+  if (!file) return;
+
+  let json = new TextDecoder().decode(await loadFile(file));
+  let savefile: Uint8Array;
+  try {
+    savefile = hades2.encode_savefile(json);
+  } catch (error) {
+    errorText.innerText = `Could not convert JSON to savefile: ${errorMessage(error)}`;
+    return;
+  }
+
+  download(new Blob([savefile]), file.name.replace(/\.json$/, ""));
+}
+
+function download(blob: Blob, name: string) {
+  let url = URL.createObjectURL(blob);
+  let link = document.createElement("a");
+  link.href = url;
+  link.download = name;
+  link.click();
+  URL.revokeObjectURL(url);
+  link.remove();
+}
+
 function expandSavefile(data: Uint8Array, format: Format): string | null {
   try {
     return hades2.expand_savefile(data, format);
   } catch (error) {
-    let text: string;
-    if (error instanceof Error) text = error.message;
-    else if (typeof error == "string") text = error;
-    else text = "" + error;
-
-    errorText.innerText = `Could not parse savefile: ${text}`;
+    errorText.innerText = `Could not parse savefile: ${errorMessage(error)}`;
     return null;
   }
 }
@@ -66,14 +99,8 @@ async function processSavefile(data: Uint8Array, format: Format) {
   let expanded = expandSavefile(data, format);
   if (!expanded) return;
 
-  let url = URL.createObjectURL(new Blob([expanded]));
-  let link = document.createElement("a");
-  link.href = url;
   let extension = format == "text" ? "txt" : "json";
-  link.download = `${fileselect.files?.[0]?.name}.${extension}`;
-  link.click();
-  URL.revokeObjectURL(url);
-  link.remove();
+  download(new Blob([expanded]), `${fileselect.files?.[0]?.name}.${extension}`);
 }
 
 let tree: jsonview.Tree;